@@ -2,31 +2,147 @@ use std::fs::{ File as STD_FILE };
 use std::io::{ BufWriter, Write };
 use std::rc::{ Rc };
 use std::cell::{ Cell, RefCell };
+use std::collections::{ HashMap };
+
+use chrono::{ DateTime, FixedOffset };
+use rand::{ SeedableRng };
+use rand::rngs::{ StdRng };
 
 use crate::category_mapper;
 use crate::common;
-use crate::common::{ BuilderState, Player };
+use crate::common::{ BuilderState, Player, RosterSlot };
+pub use crate::common::{ ObjectiveMode, StackRule };
 use crate::contest_reader::{ load_contest };
+pub use crate::correlation::{ CorrelationModel };
+use crate::exposure::{ ExposureTracker };
+pub use crate::injury::{ InjuryPolicy };
+use crate::late_swap::{ EnteredLineup, read_entered_lineups, has_started };
+pub use crate::exposure::{ ExposureRule, PlayerExposure };
+use crate::exposure::{ calculate_exposures };
+use crate::name_matcher::{ NameMatcher };
 use crate::player_pool::PlayerPool;
+use crate::projection_reader::{ read_projections, merge_projections, ProjectionSource };
+pub use crate::simulator::{ LineupSimulation, simulate_lineups };
+use crate::randomizer::{ perturb_player_pool };
+pub use crate::randomizer::{ Randomness };
 use crate::slate_reader::{ read_slate };
 use crate::lineup_optimizer::{ OptimizerContext, Optimizer };
+use crate::lineup_writer::{ write_draftkings_lineups, write_fanduel_lineups };
 use crate::lp_optimizer::{ LpOptimizer };
 
+/// A set of players, each assigned to the roster slot they fill, in the order of the contest template's slots
 pub struct Lineup {
-    player_list: Vec<Player>,
+    assignments: Vec<(RosterSlot, Player)>,
+    /// the contest entry the lineup was entered as, see `EnteredLineup::entry`
+    entry: Vec<(String, String)>,
 }
 
 impl Lineup {
-    pub fn new(player_list: Vec<Player>) -> Lineup {
+    pub fn new(assignments: Vec<(RosterSlot, Player)>) -> Lineup {
         Lineup {
-            player_list
+            assignments,
+            entry: Vec::new(),
+        }
+    }
+
+    /// Sets the contest entry the lineup replaces, written in front of the roster slots
+    pub fn with_entry(mut self, entry: Vec<(String, String)>) -> Lineup {
+        self.entry = entry;
+        self
+    }
+
+    /// Builds a lineup from the (player_id, group_id) pairs selected by the optimizer. Players are placed in the roster
+    /// slot of their group in the order of `roster_slots`, and have the slot's salary and point multipliers applied
+    pub fn from_selection(selected: &[(u64, u32)], roster_slots: &[RosterSlot], category_map: &HashMap<String, u32>, player_pool: &PlayerPool) -> Result<Lineup, String> {
+        let mut assignments: Vec<(RosterSlot, Player)> = Vec::new();
+        for slot in roster_slots {
+            let category = category_map.get(&slot.key)
+                .ok_or_else(|| format!("no category found for roster slot {}", slot.key))?;
+            let mut slot_players: Vec<Player> = Vec::new();
+            for (id, _) in selected.iter().filter(|(_, group_id)| group_id == category) {
+                let mut player = player_pool.get_player(id)
+                    .ok_or_else(|| format!("selected player {} not found in the player pool", id))?
+                    .clone();
+                player.price = (player.price as f64 * slot.salary_multiplier).round() as u32;
+                player.projected_points *= slot.point_multiplier;
+                player.std_dev *= slot.point_multiplier;
+                slot_players.push(player);
+            }
+            slot_players.sort_by(|a,b| b.partial_cmp(a).unwrap());
+            assignments.extend(slot_players.into_iter().map(|player| (slot.clone(), player)));
+        }
+        if assignments.len() != selected.len() {
+            return Err("selected players don't match the roster slots".to_string());
+        }
+        Ok(Lineup::new(assignments))
+    }
+
+    /// Fits `players` into `roster_slots` using any slot their categories allow, `None` if they don't fit
+    pub fn from_players(players: Vec<Player>, roster_slots: &[RosterSlot], category_map: &HashMap<String, u32>) -> Option<Lineup> {
+        let mut open_slots: Vec<(&RosterSlot, u32)> = Vec::new();
+        for slot in roster_slots {
+            let category = *category_map.get(&slot.key)?;
+            for _ in 0..slot.count {
+                open_slots.push((slot, category));
+            }
         }
+        if open_slots.len() != players.len() {
+            return None;
+        }
+        let mut used = vec![false; players.len()];
+        let mut assigned: Vec<usize> = Vec::new();
+        if !fill_slots(&open_slots, &players, &mut used, &mut assigned) {
+            return None;
+        }
+        let assignments = open_slots.iter().zip(assigned)
+            .map(|((slot, _), i)| ((*slot).clone(), players[i].clone()))
+            .collect();
+        Some(Lineup::new(assignments))
+    }
+
+    /// Rearranges the players so the ones in the latest games fill the flex slots (i.e. FLEX or UTIL) wherever their
+    /// positions allow it. Players only move between slots with the same salary and point multipliers
+    pub fn arrange_for_late_swap(&self, category_map: &HashMap<String, u32>) -> Lineup {
+        let mut open_slots: Vec<(&RosterSlot, u32)> = Vec::new();
+        for (slot, _) in &self.assignments {
+            match category_map.get(&slot.key) {
+                Some(category) => open_slots.push((slot, *category)),
+                None => return Lineup::new(self.assignments.clone()).with_entry(self.entry.clone()),
+            }
+        }
+        let mut search = LateSwapSearch {
+            open_slots: &open_slots,
+            assignments: &self.assignments,
+            used: vec![false; self.assignments.len()],
+            assigned: Vec::new(),
+            best: (0..self.assignments.len()).collect(),
+            best_score: late_swap_score(&self.assignments.iter().map(|(s, p)| (s, p)).collect::<Vec<_>>()),
+        };
+        search.fill();
+        let assignments = search.best.iter().zip(&open_slots)
+            .map(|(i, (slot, _))| ((*slot).clone(), self.assignments[*i].1.clone()))
+            .collect();
+        Lineup::new(assignments).with_entry(self.entry.clone())
+    }
+
+    /// Each roster slot and the player filling it
+    pub fn slots(&self) -> &Vec<(RosterSlot, Player)> {
+        &self.assignments
+    }
+
+    /// The (header, value) of each entry column, empty for new lineups
+    pub fn entry(&self) -> &Vec<(String, String)> {
+        &self.entry
+    }
+
+    pub fn players(&self) -> Vec<&Player> {
+        self.assignments.iter().map(|(_, player)| player).collect()
     }
 
     pub fn expected_result(&self) -> (f64, u32) {
         let mut point_total = 0.0;
         let mut salary_total = 0;
-        for player in &self.player_list {
+        for (_, player) in &self.assignments {
             point_total += player.projected_points;
             salary_total += player.price;
         }
@@ -35,10 +151,8 @@ impl Lineup {
 
     pub fn to_string(&self) -> String {
         let mut output = String::new();
-        let mut p = 1;
-        for player in &self.player_list {
-            output.push_str(&format!("{}. {:?}\n", p, player));
-            p += 1;
+        for (slot, player) in &self.assignments {
+            output.push_str(&format!("{}: {:?}\n", slot.key, player));
         }
         output.push_str("--------------------------\n");
         let (point_total, salary_total) = self.expected_result();
@@ -47,12 +161,101 @@ impl Lineup {
     }
 }
 
+/// Sum of the start times of the players in flex slots, higher leaves more swap options open
+fn late_swap_score(assignments: &[(&RosterSlot, &Player)]) -> i64 {
+    assignments.iter()
+        .filter(|(slot, _)| slot.flex)
+        .map(|(_, player)| player.start_time.map(|t| t.timestamp()).unwrap_or(0))
+        .sum()
+}
+
+/// Backtracking search over every valid arrangement of a lineup's players for the best late swap score
+struct LateSwapSearch<'a> {
+    open_slots: &'a [(&'a RosterSlot, u32)],
+    assignments: &'a [(RosterSlot, Player)],
+    used: Vec<bool>,
+    assigned: Vec<usize>,
+    best: Vec<usize>,
+    best_score: i64,
+}
+
+impl<'a> LateSwapSearch<'a> {
+    fn fill(&mut self) {
+        let (slot, category) = match self.open_slots.get(self.assigned.len()) {
+            Some(open_slot) => *open_slot,
+            None => {
+                let arrangement: Vec<(&RosterSlot, &Player)> = self.assigned.iter().zip(self.open_slots)
+                    .map(|(i, (slot, _))| (*slot, &self.assignments[*i].1))
+                    .collect();
+                let score = late_swap_score(&arrangement);
+                if score > self.best_score {
+                    self.best_score = score;
+                    self.best = self.assigned.clone();
+                }
+                return;
+            }
+        };
+        for i in 0..self.assignments.len() {
+            let (current_slot, player) = &self.assignments[i];
+            if self.used[i] || !player.categories.contains(&category)
+                || current_slot.salary_multiplier != slot.salary_multiplier
+                || current_slot.point_multiplier != slot.point_multiplier {
+                continue;
+            }
+            self.used[i] = true;
+            self.assigned.push(i);
+            self.fill();
+            self.assigned.pop();
+            self.used[i] = false;
+        }
+    }
+}
+
+/// Backtracking search for a player to fill each remaining open slot
+fn fill_slots(open_slots: &[(&RosterSlot, u32)], players: &[Player], used: &mut [bool], assigned: &mut Vec<usize>) -> bool {
+    let (_, category) = match open_slots.get(assigned.len()) {
+        Some(slot) => *slot,
+        None => return true,
+    };
+    for (i, player) in players.iter().enumerate() {
+        if used[i] || !player.categories.contains(&category) {
+            continue;
+        }
+        used[i] = true;
+        assigned.push(i);
+        if fill_slots(open_slots, players, used, assigned) {
+            return true;
+        }
+        assigned.pop();
+        used[i] = false;
+    }
+    false
+}
+
 pub struct Builder {
     resource_path: String,
     dfs_provider: Option<String>,
     sport: Option<String>,
     contest_type: Option<String>,
     slate_path: Option<String>,
+    num_lineups: Option<u32>,
+    min_unique: Option<u32>,
+    exposure_rules: Vec<ExposureRule>,
+    locked_ids: Vec<u64>,
+    locked_names: Vec<String>,
+    excluded_ids: Vec<u64>,
+    excluded_names: Vec<String>,
+    stack_rules: Vec<StackRule>,
+    min_salary: Option<u32>,
+    objective_mode: ObjectiveMode,
+    randomness: Option<Randomness>,
+    seed: Option<u64>,
+    projection_paths: Vec<(String, f64)>,
+    aliases_path: Option<String>,
+    games: Vec<String>,
+    starts_after: Option<DateTime<FixedOffset>>,
+    starts_before: Option<DateTime<FixedOffset>>,
+    injury_policy: InjuryPolicy,
     builder_state: Option<BuilderState>,
 }
 
@@ -64,6 +267,24 @@ impl Builder {
             sport: None,
             contest_type: None,
             slate_path: None,
+            num_lineups: None,
+            min_unique: None,
+            exposure_rules: Vec::new(),
+            locked_ids: Vec::new(),
+            locked_names: Vec::new(),
+            excluded_ids: Vec::new(),
+            excluded_names: Vec::new(),
+            stack_rules: Vec::new(),
+            min_salary: None,
+            objective_mode: ObjectiveMode::Points,
+            randomness: None,
+            seed: None,
+            projection_paths: Vec::new(),
+            aliases_path: None,
+            games: Vec::new(),
+            starts_after: None,
+            starts_before: None,
+            injury_policy: InjuryPolicy::default(),
             builder_state: None,
         }
     }
@@ -88,6 +309,125 @@ impl Builder {
         self
     }
 
+    /// Sets the number of distinct lineups to generate, defaults to 1
+    pub fn num_lineups(mut self, num_lineups: u32) -> Self {
+        self.num_lineups = Some(num_lineups);
+        self
+    }
+
+    /// Sets the minimum number of players each generated lineup must differ by from every
+    /// previously generated lineup, defaults to 1
+    pub fn min_unique(mut self, min_unique: u32) -> Self {
+        self.min_unique = Some(min_unique);
+        self
+    }
+
+    /// Limits how often a player may appear across the generated lineups. `player` is a player id or name,
+    /// `min` and `max` are percentages of the total number of lineups
+    pub fn exposure(mut self, player: &str, min: f64, max: f64) -> Self {
+        self.exposure_rules.push(ExposureRule {
+            player: String::from(player),
+            min,
+            max,
+        });
+        self
+    }
+
+    /// Forces the players with the given ids into every lineup
+    pub fn lock(mut self, player_ids: &[u64]) -> Self {
+        self.locked_ids.extend_from_slice(player_ids);
+        self
+    }
+
+    /// Forces the players with the given names into every lineup
+    pub fn lock_by_name(mut self, names: &[&str]) -> Self {
+        self.locked_names.extend(names.iter().map(|n| String::from(*n)));
+        self
+    }
+
+    /// Removes the players with the given ids from consideration
+    pub fn exclude(mut self, player_ids: &[u64]) -> Self {
+        self.excluded_ids.extend_from_slice(player_ids);
+        self
+    }
+
+    /// Removes the players with the given names from consideration
+    pub fn exclude_by_name(mut self, names: &[&str]) -> Self {
+        self.excluded_names.extend(names.iter().map(|n| String::from(*n)));
+        self
+    }
+
+    /// Adds a stacking rule on top of any defined by the contest template
+    pub fn stack(mut self, rule: StackRule) -> Self {
+        self.stack_rules.push(rule);
+        self
+    }
+
+    /// Sets the minimum total salary of each lineup, overrides the contest template
+    pub fn min_salary(mut self, min_salary: u32) -> Self {
+        self.min_salary = Some(min_salary);
+        self
+    }
+
+    /// Sets what the optimizer maximizes, i.e. penalizing or capping projected ownership. Defaults to projected points
+    pub fn objective(mut self, objective_mode: ObjectiveMode) -> Self {
+        self.objective_mode = objective_mode;
+        self
+    }
+
+    /// Randomly perturbs the projections before solving each lineup to diversify the set of lineups
+    pub fn randomness(mut self, randomness: Randomness) -> Self {
+        self.randomness = Some(randomness);
+        self
+    }
+
+    /// Seeds the random perturbation of projections so runs are reproducible
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Adds a projections file which overwrites the projected points from the slate
+    pub fn projections(self, projections_path: &str) -> Self {
+        self.weighted_projections(projections_path, 1.0)
+    }
+
+    /// Adds a projections file that is blended with any other projections files by `weight`
+    pub fn weighted_projections(mut self, projections_path: &str, weight: f64) -> Self {
+        self.projection_paths.push((String::from(projections_path), weight));
+        self
+    }
+
+    /// Sets an alias file of team and player names used when matching projections onto the slate
+    pub fn aliases(mut self, aliases_path: &str) -> Self {
+        self.aliases_path = Some(String::from(aliases_path));
+        self
+    }
+
+    /// Restricts the player pool to these games, i.e. `NYG@DAL`, for building a smaller slate out of a main slate
+    pub fn games(mut self, games: &[&str]) -> Self {
+        self.games.extend(games.iter().map(|game| game.to_uppercase()));
+        self
+    }
+
+    /// Restricts the player pool to games starting at or after `start_time`
+    pub fn starts_after(mut self, start_time: DateTime<FixedOffset>) -> Self {
+        self.starts_after = Some(start_time);
+        self
+    }
+
+    /// Restricts the player pool to games starting at or before `start_time`
+    pub fn starts_before(mut self, start_time: DateTime<FixedOffset>) -> Self {
+        self.starts_before = Some(start_time);
+        self
+    }
+
+    /// Sets how injured players are treated, defaults to excluding players that are out or on the injured list
+    pub fn injury_policy(mut self, injury_policy: InjuryPolicy) -> Self {
+        self.injury_policy = injury_policy;
+        self
+    }
+
     pub fn build(mut self) -> Result<Self, &'static str> {
         let mut path = String::new();
         path.push_str(&self.resource_path);
@@ -118,6 +458,10 @@ impl Builder {
             player_data_list: None,
             roster_slots: None,
             salary_cap: None,
+            min_salary: self.min_salary,
+            stack_rules: self.stack_rules.clone(),
+            objective_mode: self.objective_mode,
+            ..Default::default()
         };
 
         // TODO: account for unimplemented lineup settings here
@@ -125,20 +469,146 @@ impl Builder {
 
         // choose the correct mapper
         if let Some(sport) = &self.sport {
-            let mapped_indices = category_mapper::map_categories(sport).unwrap();
+            let mut mapped_indices = category_mapper::map_categories(sport).unwrap();
             load_contest(&path, &mut builder_state);
+            category_mapper::append_slot_categories(&mut mapped_indices, builder_state.roster_slots.as_ref().unwrap());
             if let Some(slate_path) = &self.slate_path {
                 read_slate(slate_path, provider, &mut builder_state, &mapped_indices)?;
             } else { // ERROR: no slate path
                 return Err("no slate path specified");
             }
+            builder_state.category_map = mapped_indices;
         } else { // ERROR: unknown sport
             return Err("no sport specified");
         }
+        self.finalize_player_pool(&mut builder_state)?;
+        self.check_randomness(&builder_state)?;
+        self.resolve_locks(&mut builder_state)?;
         self.builder_state = Some(builder_state);
         Ok(self)
     }
 
+    /// Merges any projections onto the slate and applies the injury policy, then drops the players that aren't worth
+    /// considering from the pool
+    fn finalize_player_pool(&self, builder_state: &mut BuilderState) -> Result<(), &'static str> {
+        let mut players = match &builder_state.player_pool {
+            Some(ref pp) => pp.get_all(),
+            None => return Err("no player pool available"),
+        };
+        if let Some(game) = self.games.iter().find(|game| !players.iter().any(|p| &p.game.to_uppercase() == *game)) {
+            println!("no players found in game {}", game);
+            return Err("selected game not found in the slate");
+        }
+        let has_window = self.starts_after.is_some() || self.starts_before.is_some();
+        if has_window && !players.iter().any(|p| p.start_time.is_some()) {
+            return Err("the slate has no start times (i.e. FanDuel), select games instead of a start time window");
+        }
+        players.retain(|p| self.in_slate(p));
+        if players.is_empty() {
+            return Err("no players left in the selected games");
+        }
+        if !self.projection_paths.is_empty() {
+            let mut sources: Vec<ProjectionSource> = Vec::new();
+            for (projections_path, weight) in &self.projection_paths {
+                if *weight <= 0.0 {
+                    return Err("projection weights must be positive");
+                }
+                let projections = match read_projections(projections_path) {
+                    Ok(projections) => projections,
+                    Err(err) => {
+                        println!("{}", err);
+                        return Err("failed to read projections file");
+                    }
+                };
+                sources.push(ProjectionSource { projections, weight: *weight });
+            }
+            let matcher = match &self.aliases_path {
+                Some(aliases_path) => match NameMatcher::from_alias_file(aliases_path) {
+                    Ok(matcher) => matcher,
+                    Err(err) => {
+                        println!("{}", err);
+                        return Err("failed to read alias file");
+                    }
+                },
+                None => NameMatcher::new(),
+            };
+            let report = merge_projections(&mut players, &sources, &matcher);
+            for (projection, player, confidence) in &report.low_confidence {
+                println!("low confidence match ({:.2}): {} ({}) -> {} ({})",
+                    confidence, projection.name, projection.team, player.name, player.team);
+            }
+            for projection in &report.unmatched_projections {
+                println!("no player found for projection: {} ({})", projection.name, projection.team);
+            }
+            for player in &report.unmatched_players {
+                println!("no projection found for player, keeping the slate projection: {} ({})", player.name, player.team);
+            }
+        }
+        builder_state.slate_pool = Some(PlayerPool::new(players.clone(), false));
+        for player in self.injury_policy.apply(&mut players) {
+            println!("excluding injured player: {} ({}) {} {}", player.name, player.team, player.injury_indicator, player.injury_details);
+        }
+        builder_state.player_pool = Some(PlayerPool::new(players, true));
+        Ok(())
+    }
+
+    /// Perturbing by std dev needs players with a spread, which only comes from blending projection sources
+    fn check_randomness(&self, builder_state: &BuilderState) -> Result<(), &'static str> {
+        if self.randomness != Some(Randomness::StdDev) {
+            return Ok(());
+        }
+        let has_spread = builder_state.player_pool.as_ref()
+            .is_some_and(|pp| pp.iter().any(|(_, player)| player.std_dev > 0.0));
+        if !has_spread {
+            return Err("stddev randomness requires a spread between projections, blend more than one projections file");
+        }
+        Ok(())
+    }
+
+    /// Whether the player's game is in the selected games and start time window. Players without a start time
+    /// are left out whenever a window is set
+    fn in_slate(&self, player: &Player) -> bool {
+        if !self.games.is_empty() && !self.games.contains(&player.game.to_uppercase()) {
+            return false;
+        }
+        if self.starts_after.is_none() && self.starts_before.is_none() {
+            return true;
+        }
+        match player.start_time {
+            Some(start_time) => self.starts_after.is_none_or(|after| start_time >= after)
+                && self.starts_before.is_none_or(|before| start_time <= before),
+            None => false,
+        }
+    }
+
+    /// Maps the locked and excluded players onto ids in the player pool
+    fn resolve_locks(&self, builder_state: &mut BuilderState) -> Result<(), &'static str> {
+        let player_pool = match &builder_state.player_pool {
+            Some(ref pp) => pp,
+            None => return Err("no player pool available to lock or exclude players"),
+        };
+        for id in &self.locked_ids {
+            if player_pool.get_player(id).is_none() {
+                return Err("locked player id not found in the player pool");
+            }
+            builder_state.locked_players.push(vec![*id]);
+        }
+        for name in &self.locked_names {
+            match player_pool.get_players_by_name(name) {
+                Some(players) => builder_state.locked_players.push(players.iter().map(|p| p.id).collect()),
+                None => return Err("locked player name not found in the player pool"),
+            }
+        }
+        builder_state.excluded_players.extend_from_slice(&self.excluded_ids);
+        for name in &self.excluded_names {
+            match player_pool.get_players_by_name(name) {
+                Some(players) => builder_state.excluded_players.extend(players.iter().map(|p| p.id)),
+                None => return Err("excluded player name not found in the player pool"),
+            }
+        }
+        Ok(())
+    }
+
     pub fn optimize(&self) -> Result<Vec<Lineup>, &'static str> {
         let mapped_indices = match &self.sport {
             Some(sport) => category_mapper::map_categories(sport).unwrap(),
@@ -177,8 +647,10 @@ impl Builder {
                         let player: Player = optimized_player_pool.iter().nth(*index).map(|p| p.clone()).unwrap();
                         optimal_lineup.push(player);
                     }
-                    optimal_lineup.sort_by(|a,b| b.partial_cmp(a).unwrap());
-                    let lineup = Lineup::new(optimal_lineup);
+                    let lineup = match Lineup::from_players(optimal_lineup, s.roster_slots.as_ref().unwrap(), &mapped_indices) {
+                        Some(lineup) => lineup,
+                        None => continue,
+                    };
                     lineups.push(lineup);
                     if lineups.len() >= take {
                         break;
@@ -193,44 +665,320 @@ impl Builder {
         };
     }
 
-    pub fn optimize_new(&self) -> Result<Vec<Lineup>, String> {
-        let mapped_indices = match &self.sport {
-            Some(sport) => category_mapper::map_categories(sport).unwrap(),
-            None => panic!("failed mapping categories for optimization")
+    /// Writes lineups to a csv in the provider's bulk upload format
+    pub fn write_lineups(&self, file_path: &str, lineups: &[Lineup]) -> Result<(), String> {
+        let state = match &self.builder_state {
+            Some(ref s) => s,
+            None => return Err("lineups can only be written after the builder is built".to_string()),
         };
+        let roster_slots = state.roster_slots.as_ref().ok_or_else(|| "no roster slots available".to_string())?;
+        match self.dfs_provider.as_deref() {
+            Some("draft_kings") => write_draftkings_lineups(file_path, lineups, roster_slots),
+            Some("fanduel") => write_fanduel_lineups(file_path, lineups, roster_slots),
+            Some(provider) => Err(format!("writing lineups is not supported for {}", provider)),
+            None => Err("no dfs provider specified".to_string()),
+        }
+    }
+
+    /// Exposure of every player in the pool across `lineups`, along with the range of any exposure rule
+    pub fn exposures(&self, lineups: &[Lineup]) -> Result<Vec<PlayerExposure>, String> {
+        let player_pool = self.builder_state.as_ref()
+            .and_then(|state| state.player_pool.as_ref())
+            .ok_or_else(|| "exposures can only be calculated after the builder is built".to_string())?;
+        Ok(calculate_exposures(lineups, player_pool, &self.exposure_rules))
+    }
+
+    /// Re-optimizes lineups that were already entered into contests. Players whose games have started by `now` stay
+    /// in their slot if they're in the lineup and are unavailable otherwise, the rest of each lineup is re-optimized.
+    /// Entered players that were dropped from the player pool (i.e. ruled out) are replaced unless their game has
+    /// started. A lineup that can't be improved under those constraints is returned as it was entered. Each lineup
+    /// keeps the entry it was read with so the written file edits the existing entries
+    pub fn late_swap(&self, entries_path: &str, now: &DateTime<FixedOffset>) -> Result<Vec<Lineup>, String> {
+        let state = match &self.builder_state {
+            Some(ref s) => s,
+            None => return Err("late swap requires the builder to be built".to_string()),
+        };
+        let slate_pool = state.slate_pool.as_ref().ok_or_else(|| "no slate available".to_string())?;
+        if !slate_pool.iter().any(|(_, player)| player.start_time.is_some()) {
+            return Err("late swap requires game start times, which the slate doesn't have (i.e. FanDuel)".to_string());
+        }
+        let roster_slots = state.roster_slots.as_ref().ok_or_else(|| "no roster slots available".to_string())?;
+        let mut lineups: Vec<Lineup> = Vec::new();
+        for entered in read_entered_lineups(entries_path, roster_slots, &state.category_map, slate_pool)? {
+            lineups.push(swap_entered_lineup(state, &entered, now)?);
+        }
+        Ok(lineups)
+    }
 
+    pub fn optimize_new(&self) -> Result<Vec<Lineup>, String> {
         match &self.builder_state {
             Some(ref s) => {
+                let mapped_indices = &s.category_map;
                 let player_pool: &PlayerPool = match &s.player_pool {
                     Some(ref pp) => pp,
                     None => panic!("Catastrophic error, no player pool available for optimization")
                 };
                 let mut optimizer: LpOptimizer = LpOptimizer::new(player_pool.clone());
-                optimizer.initialize(s, &mapped_indices);
-                match optimizer.solve() {
-                    Ok(ids) => {
-                        let mut lineups: Vec<Lineup> = Vec::new();
-                        let mut player_list: Vec<Player> = Vec::new();
-                        for id in ids {
-                            player_list.push(player_pool.get_player(&id).unwrap().clone());
+                optimizer.initialize(s, mapped_indices);
+                let roster_slots = s.roster_slots.as_ref().unwrap();
+                let num_lineups = self.num_lineups.unwrap_or(1);
+                let min_unique = self.min_unique.unwrap_or(1);
+                let mut exposure_tracker = ExposureTracker::new(num_lineups, &self.exposure_rules, player_pool)?;
+                let mut rng = match self.seed {
+                    Some(seed) => StdRng::seed_from_u64(seed),
+                    None => StdRng::from_entropy(),
+                };
+                let mut lineups: Vec<Lineup> = Vec::new();
+                let mut selections: Vec<Vec<(u64, u32)>> = Vec::new();
+                while lineups.len() < num_lineups as usize {
+                    if let Some(randomness) = self.randomness {
+                        // start from a fresh problem with perturbed projections, carrying over the previous lineups
+                        optimizer = LpOptimizer::new(perturb_player_pool(player_pool, randomness, &mut rng));
+                        optimizer.initialize(s, mapped_indices);
+                        for selected in &selections {
+                            optimizer.exclude_lineup(selected, min_unique);
+                        }
+                        exposure_tracker.reset();
+                    }
+                    exposure_tracker.tighten(&mut optimizer);
+                    match optimizer.solve() {
+                        Ok(selected) => {
+                            let ids: Vec<u64> = selected.iter().map(|(id, _)| *id).collect();
+                            optimizer.exclude_lineup(&selected, min_unique);
+                            exposure_tracker.record(&ids);
+                            let lineup = Lineup::from_selection(&selected, roster_slots, mapped_indices, player_pool)?;
+                            selections.push(selected);
+                            lineups.push(lineup.arrange_for_late_swap(mapped_indices));
+                        },
+                        Err(err) => {
+                            if lineups.is_empty() {
+                                return Err(format!("Error solving slate: {}", err));
+                            }
+                            // the remaining lineups are infeasible, return what was found
+                            println!("only generated {} of {} lineups: {}", lineups.len(), num_lineups, err);
+                            break;
                         }
-                        player_list.sort_by(|a,b| b.partial_cmp(a).unwrap());
-                        lineups.push(Lineup::new(player_list));
-                        return Ok(lineups);
-                    },
-                    Err(err) => {
-                        return Err(format!("Error solving slate: {}", err));
                     }
                 }
+                lineups.sort_by(|a,b| b.expected_result().0.partial_cmp(&a.expected_result().0).unwrap());
+                return Ok(lineups);
             },
             None => panic!("Catastrophic error, no state available to get roster categories, please retry"),
         }
     }
 }
 
+/// Re-optimizes a single entered lineup for late swap, keeping its entry, see `Builder::late_swap`
+fn swap_entered_lineup(state: &BuilderState, entered: &EnteredLineup, now: &DateTime<FixedOffset>) -> Result<Lineup, String> {
+    let entry = entered.entry.clone();
+    let entered = &entered.selected;
+    let player_pool = state.player_pool.as_ref().ok_or_else(|| "no player pool available".to_string())?;
+    let slate_pool = state.slate_pool.as_ref().unwrap_or(player_pool);
+    let roster_slots = state.roster_slots.as_ref().ok_or_else(|| "no roster slots available".to_string())?;
+    let mapped_indices = &state.category_map;
+
+    // entered players that have started are kept even if they were dropped from the player pool
+    let mut players = player_pool.get_all();
+    for (id, _) in entered {
+        if player_pool.get_player(id).is_none() {
+            match slate_pool.get_player(id) {
+                Some(player) if has_started(player, now) => players.push(player.clone()),
+                _ => (),
+            }
+        }
+    }
+    let swap_pool = PlayerPool::new(players, false);
+
+    let mut optimizer = LpOptimizer::new(swap_pool.clone());
+    optimizer.initialize(state, mapped_indices);
+    let mut locked: Vec<u64> = Vec::new();
+    for (id, group_id) in entered {
+        if swap_pool.get_player(id).is_some_and(|player| has_started(player, now)) {
+            optimizer.lock_player_slot(*id, *group_id)?;
+            locked.push(*id);
+        }
+    }
+    let unavailable: Vec<u64> = swap_pool.iter()
+        .filter(|(id, player)| has_started(player, now) && !locked.contains(id))
+        .map(|(id, _)| *id)
+        .collect();
+    optimizer.exclude_players(&unavailable);
+    let lineup = match optimizer.solve() {
+        Ok(selected) => Lineup::from_selection(&selected, roster_slots, mapped_indices, &swap_pool)?,
+        Err(err) => {
+            println!("keeping entered lineup, failed to re-optimize: {}", err);
+            Lineup::from_selection(entered, roster_slots, mapped_indices, slate_pool)?
+        }
+    };
+    Ok(lineup.with_entry(entry))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::{ TimeZone };
+    use crate::test_helpers::{ get_test_slots };
+
+    #[test]
+    fn lineup_from_selection() {
+        let (roster_slots, category_map) = get_test_slots();
+        let player_pool = PlayerPool::new(vec![
+            test_player(1, 20.0, hashset!{2, 5}),
+            test_player(2, 25.0, hashset!{2, 5}),
+            test_player(3, 18.0, hashset!{1}),
+        ], false);
+        let lineup = Lineup::from_selection(&[(1, 2), (2, 5), (3, 1)], &roster_slots, &category_map, &player_pool).unwrap();
+        let assigned: Vec<(&str, u64)> = lineup.slots().iter().map(|(slot, p)| (slot.key.as_str(), p.id)).collect();
+        assert_eq!(assigned, vec![("QB", 3), ("RB", 1), ("FLEX", 2)]);
+        assert_eq!(lineup.expected_result(), (63.0, 15000));
+        assert!(Lineup::from_selection(&[(1, 2), (2, 7)], &roster_slots, &category_map, &player_pool).is_err());
+    }
+
+    #[test]
+    fn lineup_from_players() {
+        let (roster_slots, category_map) = get_test_slots();
+        // both RBs can fill the FLEX slot, the first one found takes the RB slot
+        let players = vec![
+            test_player(1, 20.0, hashset!{2, 5}),
+            test_player(2, 25.0, hashset!{1}),
+            test_player(3, 18.0, hashset!{2, 5}),
+        ];
+        let lineup = Lineup::from_players(players, &roster_slots, &category_map).unwrap();
+        let ids: Vec<u64> = lineup.players().iter().map(|p| p.id).collect();
+        assert_eq!(ids, vec![2, 1, 3]);
+        assert_eq!(lineup.slots()[2].0.key, "FLEX");
+
+        let invalid = vec![test_player(1, 20.0, hashset!{1}), test_player(2, 20.0, hashset!{1}), test_player(3, 20.0, hashset!{2})];
+        assert!(Lineup::from_players(invalid, &roster_slots, &category_map).is_none());
+    }
+
+    #[test]
+    fn lineup_late_swap() {
+        let (roster_slots, category_map) = get_test_slots();
+        let mut early = test_player(1, 25.0, hashset!{2, 5});
+        early.start_time = FixedOffset::west_opt(4 * 3600).unwrap().with_ymd_and_hms(2019, 9, 8, 13, 0, 0).single();
+        let mut late = test_player(2, 20.0, hashset!{2, 5});
+        late.start_time = FixedOffset::west_opt(4 * 3600).unwrap().with_ymd_and_hms(2019, 9, 8, 16, 25, 0).single();
+        let player_pool = PlayerPool::new(vec![early, late, test_player(3, 18.0, hashset!{1})], false);
+
+        let lineup = Lineup::from_selection(&[(1, 5), (2, 2), (3, 1)], &roster_slots, &category_map, &player_pool).unwrap();
+        assert_eq!(lineup.slots()[2].1.id, 1);
+        let arranged = lineup.arrange_for_late_swap(&category_map);
+        let assigned: Vec<(&str, u64)> = arranged.slots().iter().map(|(slot, p)| (slot.key.as_str(), p.id)).collect();
+        assert_eq!(assigned, vec![("QB", 3), ("RB", 1), ("FLEX", 2)]);
+    }
+
+    #[test]
+    fn swap_dropped_players() {
+        let (roster_slots, category_map) = get_test_slots();
+        let eastern = FixedOffset::west_opt(4 * 3600).unwrap();
+        let early = eastern.with_ymd_and_hms(2019, 9, 8, 13, 0, 0).single();
+        let late = eastern.with_ymd_and_hms(2019, 9, 8, 16, 25, 0).single();
+        let now = eastern.with_ymd_and_hms(2019, 9, 8, 14, 0, 0).unwrap();
+        let mut players = vec![
+            // 1 and 2 aren't in the player pool, i.e. 1 projects too low and 2 was ruled out
+            test_player(1, 2.0, hashset!{2, 5}),
+            test_player(2, 25.0, hashset!{2, 5}),
+            test_player(3, 18.0, hashset!{1}),
+            test_player(4, 20.0, hashset!{2, 5}),
+            test_player(5, 30.0, hashset!{2, 5}),
+            test_player(6, 10.0, hashset!{2, 5}),
+        ];
+        for player in players.iter_mut() {
+            player.name = format!("Player {}", player.id);
+            player.start_time = if player.id == 1 || player.id == 5 { early } else { late };
+        }
+        let available: Vec<Player> = players.iter().filter(|p| p.id > 2).cloned().collect();
+        let state = BuilderState {
+            player_pool: Some(PlayerPool::new(available, false)),
+            slate_pool: Some(PlayerPool::new(players, false)),
+            roster_slots: Some(roster_slots),
+            salary_cap: Some(50000),
+            category_map,
+            ..Default::default()
+        };
+
+        let entered = EnteredLineup {
+            entry: vec![("Entry ID".to_string(), "1".to_string())],
+            selected: vec![(3, 1), (1, 2), (2, 5)],
+        };
+        let lineup = swap_entered_lineup(&state, &entered, &now).unwrap();
+        let assigned: Vec<(&str, u64)> = lineup.slots().iter().map(|(slot, p)| (slot.key.as_str(), p.id)).collect();
+        // 1 has started so stays in its slot, 2 is replaced by the best available player that hasn't started
+        assert_eq!(assigned, vec![("QB", 3), ("RB", 1), ("FLEX", 4)]);
+        assert_eq!(lineup.entry(), &entered.entry);
+    }
+
+    #[test]
+    fn no_start_times() {
+        let eastern = FixedOffset::west_opt(4 * 3600).unwrap();
+        let unscheduled = vec![test_player(1, 20.0, hashset!{2, 5}), test_player(2, 18.0, hashset!{1})];
+        let state = || BuilderState {
+            player_pool: Some(PlayerPool::new(unscheduled.clone(), false)),
+            slate_pool: Some(PlayerPool::new(unscheduled.clone(), false)),
+            ..Default::default()
+        };
+
+        let window = Builder::new("").starts_after(eastern.with_ymd_and_hms(2019, 9, 8, 16, 0, 0).unwrap());
+        assert!(window.finalize_player_pool(&mut state()).is_err());
+        assert!(Builder::new("").finalize_player_pool(&mut state()).is_ok());
+
+        let mut builder = Builder::new("");
+        builder.builder_state = Some(state());
+        let now = eastern.with_ymd_and_hms(2019, 9, 8, 14, 0, 0).unwrap();
+        match builder.late_swap("entries.csv", &now) {
+            Err(err) => assert!(err.contains("start times")),
+            Ok(_) => panic!("late swap without start times should fail"),
+        }
+    }
+
+    #[test]
+    fn std_dev_randomness() {
+        let mut spread = test_player(1, 20.0, hashset!{2, 5});
+        spread.std_dev = 2.5;
+        let state = |players: Vec<Player>| BuilderState { player_pool: Some(PlayerPool::new(players, false)), ..Default::default() };
+        let single_source = state(vec![test_player(1, 20.0, hashset!{2, 5}), test_player(2, 18.0, hashset!{1})]);
+        let blended = state(vec![spread, test_player(2, 18.0, hashset!{1})]);
+
+        let builder = Builder::new("").randomness(Randomness::StdDev);
+        assert!(builder.check_randomness(&single_source).is_err());
+        assert!(builder.check_randomness(&blended).is_ok());
+        assert!(Builder::new("").randomness(Randomness::Percent(10.0)).check_randomness(&single_source).is_ok());
+    }
+
+    #[test]
+    fn slate_filter() {
+        let eastern = FixedOffset::west_opt(4 * 3600).unwrap();
+        let mut early = test_player(1, 20.0, hashset!{2, 5});
+        early.game = String::from("NYG@DAL");
+        early.start_time = eastern.with_ymd_and_hms(2019, 9, 8, 13, 0, 0).single();
+        let mut late = test_player(2, 20.0, hashset!{2, 5});
+        late.game = String::from("PIT@NE");
+        late.start_time = eastern.with_ymd_and_hms(2019, 9, 8, 20, 20, 0).single();
+        let unscheduled = test_player(3, 20.0, hashset!{1});
+
+        let games = Builder::new("").games(&["nyg@dal"]);
+        assert!(games.in_slate(&early));
+        assert!(!games.in_slate(&late));
+
+        let afternoon = Builder::new("")
+            .starts_after(eastern.with_ymd_and_hms(2019, 9, 8, 16, 0, 0).unwrap())
+            .starts_before(eastern.with_ymd_and_hms(2019, 9, 8, 20, 20, 0).unwrap());
+        assert!(!afternoon.in_slate(&early));
+        assert!(afternoon.in_slate(&late));
+        assert!(!afternoon.in_slate(&unscheduled));
+        assert!(Builder::new("").in_slate(&unscheduled));
+    }
+
+    fn test_player(id: u64, projected_points: f64, categories: std::collections::HashSet<u32>) -> Player {
+        Player {
+            id,
+            categories,
+            price: 5000,
+            projected_points,
+            ..Default::default()
+        }
+    }
 
     #[test]
     fn test_refcell_copying() {
@@ -1,4 +1,5 @@
 use std::collections::{ HashMap, HashSet };
+use crate::common::{ RosterSlot };
 
 trait CategoryMapper {
     fn get_all_categories() -> Vec<String>;
@@ -27,6 +28,18 @@ pub fn map_categories(sport: &str) -> Option<HashMap<String, u32>> {
     None
 }
 
+/// Adds a category for any roster slot key the sport's mapper doesn't know about (i.e. FanDuel's STAR and PRO slots),
+/// so new slot types can be added with just a contest template
+pub fn append_slot_categories(categories: &mut HashMap<String, u32>, roster_slots: &[RosterSlot]) {
+    let mut next = categories.values().max().map(|max| max + 1).unwrap_or(0);
+    for slot in roster_slots {
+        if !categories.contains_key(&slot.key) {
+            categories.insert(slot.key.clone(), next);
+            next += 1;
+        }
+    }
+}
+
 /// these are DFS positions for the NBA (at least on draft kings...)
 pub enum NBAPositions {
     PointGuard = 0,
@@ -89,6 +102,8 @@ impl CategoryMapper for NFLCategoryMapper {
             String::from("DST"),
             String::from("D"),
             String::from("CPT"),
+            String::from("K"),
+            String::from("MVP"),
         ]
     }
 }
@@ -164,6 +179,24 @@ mod tests {
         assert!(cats.contains(&(NBAPositions::UTIL as u32)));
     }
 
+    #[test]
+    fn append_unknown_slot_keys() {
+        let mut mapper = get_nba_mapper();
+        let slots: Vec<RosterSlot> = ["UTIL", "STAR", "PRO"].iter().map(|key| RosterSlot {
+            name: key.to_string(),
+            key: key.to_string(),
+            count: 1,
+            salary_multiplier: 1.0,
+            point_multiplier: 1.0,
+            any_position: true,
+            flex: false,
+        }).collect();
+        append_slot_categories(&mut mapper, &slots);
+        assert_eq!(*mapper.get("UTIL").unwrap(), NBAPositions::UTIL as u32);
+        assert_eq!(*mapper.get("STAR").unwrap(), 9);
+        assert_eq!(*mapper.get("PRO").unwrap(), 10);
+    }
+
     fn get_nba_mapper() -> HashMap<String, u32> {
         map_categories("nba").unwrap()
     }
@@ -1,5 +1,6 @@
 use std::cmp::{ Ordering };
 use std::collections::{ HashMap, HashSet };
+use chrono::{ DateTime, FixedOffset };
 use serde::{ Deserialize, Serialize };
 use crate::player_pool::PlayerPool;
 
@@ -10,23 +11,57 @@ pub struct RosterSlot {
     pub count: u32,
     pub salary_multiplier: f64,
     pub point_multiplier: f64,
+    /// any player can fill this slot regardless of position, i.e. FanDuel single game MVP
+    #[serde(default)]
+    pub any_position: bool,
+    /// more than one position can fill this slot, i.e. FLEX or UTIL. Players from the latest games are placed here to
+    /// keep late swap options open
+    #[serde(default)]
+    pub flex: bool,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct Player {
     pub id: u64,
+    /// the provider's own id for the player, i.e. FanDuel's `37319-104449`
+    pub provider_id: String,
     pub name: String,
     pub team: String,
+    pub opponent: String,
+    pub home_team: String,
+    pub away_team: String,
+    /// identifies the game the player is in, i.e. `NYG@DAL`
+    pub game: String,
+    /// scheduled start of the player's game in the provider's timezone, if the provider includes it
+    pub start_time: Option<DateTime<FixedOffset>>,
     pub position: String,
     pub categories: HashSet<u32>,
     pub price: u32,
-    pub projected_points: f64
+    /// mean projection, blended across sources when multiple projection files are used
+    pub projected_points: f64,
+    /// spread of the projection sources around `projected_points`
+    pub std_dev: f64,
+    /// projected ownership as a percentage (0-100)
+    pub ownership: f64,
+    /// the provider's injury status, i.e. FanDuel's `IL`, `O`, `Q` or `D`, empty when healthy
+    pub injury_indicator: String,
+    pub injury_details: String,
 }
 
 impl Player {
     pub fn get_value(&self) -> f64{
         return (self.projected_points) / (self.price as f64);
     }
+
+    /// Identifies the real player behind an entry, shared by all of their entries (i.e. showdown CPT and FLEX)
+    pub fn identity_key(&self) -> (String, String, String) {
+        (self.name.clone(), self.team.clone(), self.position.clone())
+    }
+
+    /// Checks if any of the player's positions (i.e. `1B/OF`) is in `positions`
+    pub fn has_position(&self, positions: &[String]) -> bool {
+        self.position.split('/').any(|pos| positions.iter().any(|p| p == pos))
+    }
 }
 
 impl PartialOrd for Player {
@@ -43,12 +78,74 @@ impl PartialEq for Player {
 
 impl Eq for Player { }
 
+/// Requires players to be stacked with a teammate, i.e. "if a QB is selected at least 2 WR/TE from the same team
+/// must also be selected". Positions are matched against `Player::position`
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct StackRule {
+    /// positions which trigger the stack
+    pub positions: Vec<String>,
+    /// positions from the same team that must be stacked with the triggering player
+    pub stack_positions: Vec<String>,
+    pub count: u32,
+    /// positions from the opposing team that must be stacked with the triggering player
+    #[serde(default)]
+    pub bring_back_positions: Vec<String>,
+    #[serde(default)]
+    pub bring_back_count: u32,
+}
+
+/// Caps the number of players from a single team at any of `positions`, i.e. at most 5 hitters in DK MLB
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct TeamLimit {
+    pub positions: Vec<String>,
+    pub max: u32,
+}
+
+/// What the optimizer maximizes for each lineup
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ObjectiveMode {
+    /// maximize projected points
+    #[default]
+    Points,
+    /// maximize projected points minus lambda times the projected ownership of each player
+    OwnershipPenalty(f64),
+    /// maximize projected points while keeping the total projected ownership of the lineup under a cap
+    OwnershipCap(f64),
+}
+
+#[derive(Default)]
 pub struct BuilderState {
     pub player_pool: Option<PlayerPool>,
+    /// every player on the slate with projections merged, including those later dropped from `player_pool` (i.e.
+    /// injured or low projection players). Used to look up players in lineups that were already entered
+    pub slate_pool: Option<PlayerPool>,
     /// DEPRECATED
     pub player_data_list: Option<Vec<Player>>,
     pub roster_slots: Option<Vec<RosterSlot>>,
     pub salary_cap: Option<u32>,
+    /// the lineup must use at least this much of the salary cap
+    pub min_salary: Option<u32>,
+    /// Each entry is a list of ids for a single player that must be in the lineup
+    pub locked_players: Vec<Vec<u64>>,
+    pub excluded_players: Vec<u64>,
+    pub stack_rules: Vec<StackRule>,
+    pub max_per_team: Option<u32>,
+    pub team_limits: Vec<TeamLimit>,
+    pub min_games: Option<u32>,
+    pub objective_mode: ObjectiveMode,
+    /// maps position and roster slot keys to category ids
+    pub category_map: HashMap<String, u32>,
+}
+
+/// Maps each roster slot to the category id of its key
+pub fn map_roster_slots(roster_slots: &[RosterSlot], category_map: &HashMap<String, u32>) -> HashMap<u32, RosterSlot> {
+    let mut slot_map: HashMap<u32, RosterSlot> = HashMap::new();
+    for slot in roster_slots {
+        if let Some(category) = category_map.get(&slot.key) {
+            slot_map.insert(*category, slot.clone());
+        }
+    }
+    slot_map
 }
 
 pub fn calculate_category_count(builder_state: &BuilderState, category_map: &HashMap<String, u32>) -> Vec<u32> {
@@ -83,9 +180,30 @@ mod tests {
             position: String::from("waterboy"),
             price: 5000,
             projected_points: 20.0,
-            categories: HashSet::new()
+            categories: HashSet::new(),
+            ..Default::default()
         };
         assert_eq!(player.get_value(), 0.004);
     }
 
+    #[test]
+    fn player_has_position() {
+        let player = Player {
+            position: String::from("1B/OF"),
+            ..Default::default()
+        };
+        assert!(player.has_position(&["OF".to_string()]));
+        assert!(player.has_position(&["C".to_string(), "1B".to_string()]));
+        assert!(!player.has_position(&["SP".to_string(), "RP".to_string()]));
+    }
+
+    #[test]
+    fn player_identity_key() {
+        let captain = Player { id: 1, name: String::from("Saquon Barkley"), team: String::from("NYG"), position: String::from("RB"), ..Default::default() };
+        let flex = Player { id: 2, ..captain.clone() };
+        let namesake = Player { id: 3, team: String::from("PHI"), ..captain.clone() };
+        assert_eq!(captain.identity_key(), flex.identity_key());
+        assert_ne!(captain.identity_key(), namesake.identity_key());
+    }
+
 }
@@ -2,13 +2,23 @@ use std::fs::{ File };
 use std::io::{ BufReader, Read };
 use serde::{ Deserialize, Serialize };
 
-use crate::common::{ BuilderState, RosterSlot };
+use crate::common::{ BuilderState, RosterSlot, StackRule, TeamLimit };
 
 /// Just an intermediary struct for serializing/deserialize this data
 #[derive(Debug, Deserialize, Serialize)]
 struct Contest {
     salary_cap: u32,
+    #[serde(default)]
+    min_salary: Option<u32>,
     slots: Vec<RosterSlot>,
+    #[serde(default)]
+    stacks: Vec<StackRule>,
+    #[serde(default)]
+    max_per_team: Option<u32>,
+    #[serde(default)]
+    team_limits: Vec<TeamLimit>,
+    #[serde(default)]
+    min_games: Option<u32>,
     // these are only here for serialization
     sport_type: String,
     contest_type: String,
@@ -28,6 +38,13 @@ pub fn load_contest(file_path: &str, builder_state: &mut BuilderState) {
                             .expect("failed to parse contest template file");
 
     builder_state.salary_cap = Some(contest.salary_cap);
+    if builder_state.min_salary.is_none() {
+        builder_state.min_salary = contest.min_salary;
+    }
     builder_state.roster_slots = Some(contest.slots);
+    builder_state.stack_rules.extend(contest.stacks);
+    builder_state.max_per_team = contest.max_per_team;
+    builder_state.team_limits = contest.team_limits;
+    builder_state.min_games = contest.min_games;
 }
 
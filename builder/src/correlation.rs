@@ -0,0 +1,204 @@
+use std::collections::{ HashMap };
+use std::io::{ Read };
+use serde::{ Deserialize, Serialize };
+use crate::common::{ Player };
+
+/// Matches any position in a correlation table
+const ANY_POSITION: &str = "*";
+
+/// How two players are related in a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Relation {
+    Teammate,
+    Opponent,
+}
+
+/// Correlation between player outcomes, keyed on the positions of the two players and whether they are teammates
+/// or opponents. Players in different games are uncorrelated
+#[derive(Debug, Clone, Default)]
+pub struct CorrelationModel {
+    correlations: HashMap<(String, String, Relation), f64>,
+}
+
+impl CorrelationModel {
+    pub fn new() -> CorrelationModel {
+        CorrelationModel::default()
+    }
+
+    /// The default correlation table for a sport, sports without a table are uncorrelated
+    pub fn for_sport(sport: &str) -> CorrelationModel {
+        let table: &[(&str, &str, Relation, f64)] = match sport.to_lowercase().as_str() {
+            "nfl" => &NFL_CORRELATIONS,
+            "mlb" => &MLB_CORRELATIONS,
+            "nba" => &NBA_CORRELATIONS,
+            _ => &[],
+        };
+        let mut model = CorrelationModel::new();
+        for (position, other, relation, correlation) in table {
+            model.set(position, other, *relation, *correlation);
+        }
+        model
+    }
+
+    /// Loads a correlation table with the columns Position,OtherPosition,Relation,Correlation where Relation is
+    /// either `team` or `opponent`. A position of `*` matches any position
+    pub fn from_file(file_path: &str) -> Result<CorrelationModel, String> {
+        let reader = csv::Reader::from_path(file_path)
+            .map_err(|err| format!("failed to open correlation file {}: {}", file_path, err))?;
+        CorrelationModel::from_rows(reader)
+    }
+
+    fn from_rows<R: Read>(mut reader: csv::Reader<R>) -> Result<CorrelationModel, String> {
+        let mut model = CorrelationModel::new();
+        for result in reader.deserialize::<CorrelationDataRow>() {
+            let record = result.map_err(|err| format!("error parsing correlation csv: {}", err))?;
+            let relation = match record.relation.trim().to_lowercase().as_str() {
+                "team" => Relation::Teammate,
+                "opponent" => Relation::Opponent,
+                other => return Err(format!("unknown correlation relation '{}'", other)),
+            };
+            if record.correlation < -1.0 || record.correlation > 1.0 {
+                return Err(format!("correlation between {} and {} must be between -1 and 1", record.position, record.other_position));
+            }
+            model.set(record.position.trim(), record.other_position.trim(), relation, record.correlation);
+        }
+        Ok(model)
+    }
+
+    /// Sets the correlation between two positions, correlations are symmetric
+    pub fn set(&mut self, position: &str, other: &str, relation: Relation, correlation: f64) {
+        self.correlations.insert((position.to_string(), other.to_string(), relation), correlation);
+        self.correlations.insert((other.to_string(), position.to_string(), relation), correlation);
+    }
+
+    /// Correlation between the outcomes of two different players. Positions are matched on each player's
+    /// primary position, falling back to any `*` entries
+    pub fn correlation(&self, player: &Player, other: &Player) -> f64 {
+        let relation = if player.team == other.team {
+            Relation::Teammate
+        } else if player.opponent == other.team || other.opponent == player.team {
+            Relation::Opponent
+        } else {
+            return 0.0;
+        };
+        let position = primary_position(player);
+        let other_position = primary_position(other);
+        let keys = [
+            (position, other_position),
+            (position, ANY_POSITION),
+            (ANY_POSITION, other_position),
+            (ANY_POSITION, ANY_POSITION),
+        ];
+        keys.iter()
+            .filter_map(|(a, b)| self.correlations.get(&(a.to_string(), b.to_string(), relation)))
+            .cloned()
+            .next()
+            .unwrap_or(0.0)
+    }
+}
+
+fn primary_position(player: &Player) -> &str {
+    player.position.split('/').next().unwrap_or("")
+}
+
+const NFL_CORRELATIONS: [(&str, &str, Relation, f64); 19] = [
+    ("QB", "WR", Relation::Teammate, 0.35),
+    ("QB", "TE", Relation::Teammate, 0.3),
+    ("QB", "RB", Relation::Teammate, 0.1),
+    ("QB", "K", Relation::Teammate, 0.1),
+    ("WR", "WR", Relation::Teammate, -0.05),
+    ("WR", "TE", Relation::Teammate, -0.05),
+    ("RB", "RB", Relation::Teammate, -0.2),
+    ("RB", "DST", Relation::Teammate, 0.15),
+    ("RB", "D", Relation::Teammate, 0.15),
+    ("QB", "QB", Relation::Opponent, 0.2),
+    ("QB", "WR", Relation::Opponent, 0.15),
+    ("QB", "TE", Relation::Opponent, 0.1),
+    ("WR", "WR", Relation::Opponent, 0.1),
+    ("DST", "QB", Relation::Opponent, -0.35),
+    ("DST", "RB", Relation::Opponent, -0.2),
+    ("DST", "WR", Relation::Opponent, -0.2),
+    ("D", "QB", Relation::Opponent, -0.35),
+    ("D", "RB", Relation::Opponent, -0.2),
+    ("D", "WR", Relation::Opponent, -0.2),
+];
+
+const MLB_CORRELATIONS: [(&str, &str, Relation, f64); 7] = [
+    ("*", "*", Relation::Teammate, 0.15),
+    ("SP", "*", Relation::Teammate, 0.0),
+    ("RP", "*", Relation::Teammate, 0.0),
+    ("P", "*", Relation::Teammate, 0.0),
+    ("SP", "*", Relation::Opponent, -0.25),
+    ("RP", "*", Relation::Opponent, -0.15),
+    ("P", "*", Relation::Opponent, -0.25),
+];
+
+const NBA_CORRELATIONS: [(&str, &str, Relation, f64); 2] = [
+    ("*", "*", Relation::Teammate, -0.05),
+    ("*", "*", Relation::Opponent, 0.05),
+];
+
+#[derive(Debug, Deserialize, Serialize)]
+struct CorrelationDataRow {
+    #[serde(rename = "Position")]
+    position: String,
+
+    #[serde(rename = "OtherPosition")]
+    other_position: String,
+
+    #[serde(rename = "Relation")]
+    relation: String,
+
+    #[serde(rename = "Correlation")]
+    correlation: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nfl_correlations() {
+        let model = CorrelationModel::for_sport("NFL");
+        let qb = test_player("QB", "NYG", "DAL");
+        let wr = test_player("WR", "NYG", "DAL");
+        let dst = test_player("DST", "DAL", "NYG");
+        let other = test_player("WR", "PHI", "WAS");
+        assert_eq!(model.correlation(&qb, &wr), 0.35);
+        assert_eq!(model.correlation(&wr, &qb), 0.35);
+        assert_eq!(model.correlation(&dst, &qb), -0.35);
+        assert_eq!(model.correlation(&qb, &other), 0.0);
+    }
+
+    #[test]
+    fn wildcard_correlations() {
+        let model = CorrelationModel::for_sport("mlb");
+        let pitcher = test_player("SP", "NYY", "BOS");
+        let hitter = test_player("1B/OF", "BOS", "NYY");
+        let teammate = test_player("SS", "BOS", "NYY");
+        assert_eq!(model.correlation(&pitcher, &hitter), -0.25);
+        assert_eq!(model.correlation(&hitter, &teammate), 0.15);
+    }
+
+    #[test]
+    fn custom_correlations() {
+        let data = "Position,OtherPosition,Relation,Correlation\nQB,WR,team,0.5\n*,*,opponent,0.1\n";
+        let model = CorrelationModel::from_rows(csv::Reader::from_reader(data.as_bytes())).unwrap();
+        let qb = test_player("QB", "NYG", "DAL");
+        assert_eq!(model.correlation(&qb, &test_player("WR", "NYG", "DAL")), 0.5);
+        assert_eq!(model.correlation(&qb, &test_player("RB", "NYG", "DAL")), 0.0);
+        assert_eq!(model.correlation(&qb, &test_player("RB", "DAL", "NYG")), 0.1);
+
+        let invalid = "Position,OtherPosition,Relation,Correlation\nQB,WR,team,1.5\n";
+        assert!(CorrelationModel::from_rows(csv::Reader::from_reader(invalid.as_bytes())).is_err());
+    }
+
+    fn test_player(position: &str, team: &str, opponent: &str) -> Player {
+        Player {
+            position: String::from(position),
+            team: String::from(team),
+            opponent: String::from(opponent),
+            ..Default::default()
+        }
+    }
+}
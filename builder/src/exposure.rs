@@ -0,0 +1,276 @@
+use std::collections::{ HashMap };
+use crate::builder::{ Lineup };
+use crate::lp_optimizer::{ LpOptimizer };
+use crate::player_pool::{ PlayerPool };
+
+/// Min/max exposure of a player across a multi-lineup run.
+/// `player` is either a `Player::id` or a player name, `min` and `max` are percentages (0-100)
+#[derive(Debug, Clone)]
+pub struct ExposureRule {
+    pub player: String,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Final exposure of a single player across a set of lineups
+#[derive(Debug, Clone)]
+pub struct PlayerExposure {
+    pub name: String,
+    pub team: String,
+    pub count: u32,
+    pub percent: f64,
+    /// the min and max percentages of the player's exposure rule, if they have one
+    pub target: Option<(f64, f64)>,
+}
+
+impl PlayerExposure {
+    /// Whether the player's exposure rule asked for more lineups than the player ended up in
+    pub fn missed_min(&self) -> bool {
+        self.target.is_some_and(|(min, _)| self.percent < min)
+    }
+}
+
+/// Tracks how often each player with an exposure rule has been used and tightens
+/// the optimizer as lineups are generated
+pub struct ExposureTracker {
+    num_lineups: u32,
+    generated: u32,
+    groups: Vec<ExposureGroup>,
+}
+
+struct ExposureGroup {
+    /// every player id the rule applies to, a name can map to multiple ids
+    player_ids: Vec<u64>,
+    min_count: u32,
+    max_count: u32,
+    count: u32,
+    locked: bool,
+    excluded: bool,
+}
+
+impl ExposureTracker {
+    pub fn new(num_lineups: u32, rules: &[ExposureRule], player_pool: &PlayerPool) -> Result<ExposureTracker, String> {
+        let mut groups: Vec<ExposureGroup> = Vec::new();
+        for rule in rules {
+            if rule.min < 0.0 || rule.max > 100.0 || rule.min > rule.max {
+                return Err(format!("invalid exposure range for '{}': {}-{}", rule.player, rule.min, rule.max));
+            }
+            let player_ids = resolve_player_ids(&rule.player, player_pool)
+                .ok_or_else(|| format!("no player found for exposure rule: {}", rule.player))?;
+            let min_count = (rule.min / 100.0 * num_lineups as f64).ceil() as u32;
+            let max_count = (rule.max / 100.0 * num_lineups as f64).floor() as u32;
+            if min_count > max_count {
+                return Err(format!("exposure range for '{}' can not be met with {} lineups", rule.player, num_lineups));
+            }
+            groups.push(ExposureGroup {
+                player_ids,
+                min_count,
+                max_count,
+                count: 0,
+                locked: false,
+                excluded: false,
+            });
+        }
+
+        Ok(ExposureTracker {
+            num_lineups,
+            generated: 0,
+            groups,
+        })
+    }
+
+    /// Records a generated lineup
+    pub fn record(&mut self, player_ids: &[u64]) {
+        self.generated += 1;
+        for group in &mut self.groups {
+            if group.player_ids.iter().any(|id| player_ids.contains(id)) {
+                group.count += 1;
+            }
+        }
+    }
+
+    /// Forgets which constraints have been added, used when lineups continue on a new optimizer
+    pub fn reset(&mut self) {
+        for group in &mut self.groups {
+            group.locked = false;
+            group.excluded = false;
+        }
+    }
+
+    /// Adds constraints to the optimizer for any player that has hit their max exposure, or that
+    /// must be in every remaining lineup to reach their min exposure. Both conditions hold for the rest
+    /// of the run once they are reached, so each constraint is only added once.
+    pub fn tighten(&mut self, optimizer: &mut LpOptimizer) {
+        let remaining = self.num_lineups.saturating_sub(self.generated);
+        for group in &mut self.groups {
+            if !group.excluded && group.count >= group.max_count {
+                optimizer.exclude_players(&group.player_ids);
+                group.excluded = true;
+            }
+            if !group.locked && !group.excluded && group.min_count.saturating_sub(group.count) >= remaining && remaining > 0 {
+                optimizer.lock_players(&group.player_ids);
+                group.locked = true;
+            }
+        }
+    }
+}
+
+/// Resolves a player reference to a list of ids, the reference is first treated as a `Player::id`
+/// and falls back to a lookup by name
+pub fn resolve_player_ids(player: &str, player_pool: &PlayerPool) -> Option<Vec<u64>> {
+    if let Ok(id) = player.parse::<u64>() {
+        if player_pool.get_player(&id).is_some() {
+            return Some(vec![id]);
+        }
+    }
+    player_pool.get_players_by_name(player)
+        .map(|players| players.iter().map(|p| p.id).collect())
+}
+
+/// Calculates the exposure of every player in `player_pool` or `lineups`, including those in no lineup, sorted from most
+/// to least exposed. Entries are grouped by `Player::identity_key`
+pub fn calculate_exposures(lineups: &[Lineup], player_pool: &PlayerPool, rules: &[ExposureRule]) -> Vec<PlayerExposure> {
+    let mut counts: HashMap<(String, String, String), u32> = HashMap::new();
+    for (_, player) in player_pool.iter() {
+        counts.entry(player.identity_key()).or_insert(0);
+    }
+    for lineup in lineups {
+        for player in lineup.players() {
+            *counts.entry(player.identity_key()).or_insert(0) += 1;
+        }
+    }
+    let mut targets: HashMap<(String, String, String), (f64, f64)> = HashMap::new();
+    for rule in rules {
+        for id in resolve_player_ids(&rule.player, player_pool).unwrap_or_default() {
+            if let Some(player) = player_pool.get_player(&id) {
+                targets.insert(player.identity_key(), (rule.min, rule.max));
+            }
+        }
+    }
+    let mut exposures: Vec<PlayerExposure> = counts.into_iter()
+        .map(|(key, count)| PlayerExposure {
+            target: targets.get(&key).copied(),
+            name: key.0,
+            team: key.1,
+            count,
+            percent: if lineups.is_empty() { 0.0 } else { count as f64 / lineups.len() as f64 * 100.0 },
+        })
+        .collect();
+    exposures.sort_by(|a,b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    exposures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{ Player, RosterSlot };
+
+    #[test]
+    fn exposure_counts() {
+        let pool = get_test_pool();
+        let rules = vec![ExposureRule { player: "Saquon Barkley".to_string(), min: 10.0, max: 40.0 }];
+        let tracker = ExposureTracker::new(20, &rules, &pool).unwrap();
+        assert_eq!(tracker.groups[0].player_ids, vec![4]);
+        assert_eq!(tracker.groups[0].min_count, 2);
+        assert_eq!(tracker.groups[0].max_count, 8);
+    }
+
+    #[test]
+    fn exposure_by_id() {
+        let pool = get_test_pool();
+        let rules = vec![ExposureRule { player: "2".to_string(), min: 0.0, max: 50.0 }];
+        let tracker = ExposureTracker::new(4, &rules, &pool).unwrap();
+        assert_eq!(tracker.groups[0].player_ids, vec![2]);
+    }
+
+    #[test]
+    fn exposure_invalid_rules() {
+        let pool = get_test_pool();
+        let unknown = vec![ExposureRule { player: "Nobody".to_string(), min: 0.0, max: 50.0 }];
+        assert!(ExposureTracker::new(4, &unknown, &pool).is_err());
+        let unreachable = vec![ExposureRule { player: "Todd Gurley".to_string(), min: 30.0, max: 40.0 }];
+        assert!(ExposureTracker::new(2, &unreachable, &pool).is_err());
+    }
+
+    #[test]
+    fn exposure_record() {
+        let pool = get_test_pool();
+        let rules = vec![ExposureRule { player: "Todd Gurley".to_string(), min: 0.0, max: 50.0 }];
+        let mut tracker = ExposureTracker::new(4, &rules, &pool).unwrap();
+        tracker.record(&[2, 4]);
+        tracker.record(&[4]);
+        assert_eq!(tracker.generated, 2);
+        assert_eq!(tracker.groups[0].count, 1);
+    }
+
+    #[test]
+    fn exposure_report() {
+        let pool = get_test_pool();
+        let lineups = vec![
+            Lineup::new(vec![(test_slot(), pool.get_player(&2).unwrap().clone()), (test_slot(), pool.get_player(&4).unwrap().clone())]),
+            Lineup::new(vec![(test_slot(), pool.get_player(&4).unwrap().clone())]),
+        ];
+        let exposures = calculate_exposures(&lineups, &pool, &[]);
+        assert_eq!(exposures.len(), 2);
+        assert_eq!(exposures[0].name, "Saquon Barkley");
+        assert_eq!(exposures[0].percent, 100.0);
+        assert_eq!(exposures[1].percent, 50.0);
+    }
+
+    #[test]
+    fn exposure_report_unused() {
+        let pool = get_test_pool();
+        let lineups = vec![Lineup::new(vec![(test_slot(), pool.get_player(&4).unwrap().clone())])];
+        let rules = vec![
+            ExposureRule { player: "Todd Gurley".to_string(), min: 20.0, max: 50.0 },
+            ExposureRule { player: "Saquon Barkley".to_string(), min: 50.0, max: 100.0 },
+        ];
+        let exposures = calculate_exposures(&lineups, &pool, &rules);
+        assert_eq!(exposures.len(), 2);
+        assert_eq!(exposures[0].name, "Saquon Barkley");
+        assert!(!exposures[0].missed_min());
+        assert_eq!(exposures[1].name, "Todd Gurley");
+        assert_eq!(exposures[1].count, 0);
+        assert_eq!(exposures[1].percent, 0.0);
+        assert_eq!(exposures[1].target, Some((20.0, 50.0)));
+        assert!(exposures[1].missed_min());
+    }
+
+    fn test_slot() -> RosterSlot {
+        RosterSlot {
+            name: String::from("Runningback"),
+            key: String::from("RB"),
+            count: 2,
+            salary_multiplier: 1.0,
+            point_multiplier: 1.0,
+            any_position: false,
+            flex: false,
+        }
+    }
+
+    fn get_test_pool() -> PlayerPool {
+        let players = vec![
+            Player {
+                id: 2,
+                name: String::from("Todd Gurley"),
+                team: String::from("LAR"),
+                position: String::from("RB"),
+                categories: hashset!{2},
+                price: 7000,
+                projected_points: 21.2,
+                ..Default::default()
+            },
+            Player {
+                id: 4,
+                name: String::from("Saquon Barkley"),
+                team: String::from("NYG"),
+                position: String::from("RB"),
+                categories: hashset!{2},
+                price: 7300,
+                projected_points: 26.0,
+                ..Default::default()
+            },
+        ];
+        PlayerPool::new(players, true)
+    }
+}
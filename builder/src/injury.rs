@@ -0,0 +1,114 @@
+use crate::common::{ Player };
+
+/// How players are treated based on their injury indicator (i.e. FanDuel's IL, O, Q and D) before optimizing.
+/// Indicators are case insensitive, players with an indicator the policy doesn't mention are kept as is
+#[derive(Debug, Clone, PartialEq)]
+pub struct InjuryPolicy {
+    excluded: Vec<String>,
+    penalties: Vec<(String, f64)>,
+}
+
+impl Default for InjuryPolicy {
+    /// Excludes players that are out or on the injured list (IL in MLB, IR in NFL), keeps everyone else
+    fn default() -> Self {
+        InjuryPolicy {
+            excluded: vec![String::from("O"), String::from("IL"), String::from("IR")],
+            penalties: Vec::new(),
+        }
+    }
+}
+
+impl InjuryPolicy {
+    pub fn new() -> InjuryPolicy {
+        InjuryPolicy::default()
+    }
+
+    /// A policy that keeps every player regardless of their injury indicator
+    pub fn keep_all() -> InjuryPolicy {
+        InjuryPolicy {
+            excluded: Vec::new(),
+            penalties: Vec::new(),
+        }
+    }
+
+    /// Removes players with this indicator from the player pool
+    pub fn exclude(mut self, indicator: &str) -> Self {
+        let indicator = indicator.trim().to_uppercase();
+        self.penalties.retain(|(i, _)| i != &indicator);
+        if !self.excluded.contains(&indicator) {
+            self.excluded.push(indicator);
+        }
+        self
+    }
+
+    /// Reduces the projected points of players with this indicator by `percent`
+    pub fn penalize(mut self, indicator: &str, percent: f64) -> Self {
+        let indicator = indicator.trim().to_uppercase();
+        self.excluded.retain(|i| i != &indicator);
+        self.penalties.retain(|(i, _)| i != &indicator);
+        self.penalties.push((indicator, percent));
+        self
+    }
+
+    /// Keeps players with this indicator as is
+    pub fn keep(mut self, indicator: &str) -> Self {
+        let indicator = indicator.trim().to_uppercase();
+        self.excluded.retain(|i| i != &indicator);
+        self.penalties.retain(|(i, _)| i != &indicator);
+        self
+    }
+
+    pub fn is_excluded(&self, player: &Player) -> bool {
+        self.excluded.contains(&player.injury_indicator.to_uppercase())
+    }
+
+    /// Removes excluded players and penalizes projections in place, returns the players that were removed
+    pub fn apply(&self, players: &mut Vec<Player>) -> Vec<Player> {
+        let (excluded, kept): (Vec<Player>, Vec<Player>) = players.drain(..).partition(|p| self.is_excluded(p));
+        *players = kept;
+        for player in players.iter_mut() {
+            let indicator = player.injury_indicator.to_uppercase();
+            if let Some((_, percent)) = self.penalties.iter().find(|(i, _)| i == &indicator) {
+                player.projected_points *= 1.0 - percent / 100.0;
+            }
+        }
+        excluded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy() {
+        let mut players = get_test_players();
+        let excluded = InjuryPolicy::default().apply(&mut players);
+        assert_eq!(excluded.iter().map(|p| p.id).collect::<Vec<u64>>(), vec![2, 3]);
+        assert_eq!(players.iter().map(|p| (p.id, p.projected_points)).collect::<Vec<(u64, f64)>>(),
+            vec![(1, 20.0), (4, 20.0), (5, 20.0)]);
+    }
+
+    #[test]
+    fn configured_policy() {
+        let mut players = get_test_players();
+        let policy = InjuryPolicy::keep_all()
+            .exclude("o")
+            .penalize("Q", 25.0)
+            .exclude("D")
+            .keep("D");
+        let excluded = policy.apply(&mut players);
+        assert_eq!(excluded.iter().map(|p| p.id).collect::<Vec<u64>>(), vec![2]);
+        assert_eq!(players.iter().map(|p| (p.id, p.projected_points)).collect::<Vec<(u64, f64)>>(),
+            vec![(1, 20.0), (3, 20.0), (4, 15.0), (5, 20.0)]);
+    }
+
+    fn get_test_players() -> Vec<Player> {
+        ["", "O", "IL", "Q", "D"].iter().enumerate().map(|(i, indicator)| Player {
+            id: i as u64 + 1,
+            injury_indicator: indicator.to_string(),
+            projected_points: 20.0,
+            ..Default::default()
+        }).collect()
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::{ HashMap };
+use std::io::{ Read };
+use chrono::{ DateTime, FixedOffset };
+use crate::common::{ Player, RosterSlot };
+use crate::player_pool::{ PlayerPool };
+
+/// A lineup that was already entered into a contest
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnteredLineup {
+    /// the (header, value) of each column in front of the roster slots, i.e. DraftKings' Entry ID, Contest Name,
+    /// Contest ID and Entry Fee. Written back with the re-optimized lineup so it edits the same entry
+    pub entry: Vec<(String, String)>,
+    /// the (player_id, group_id) of every player in the lineup
+    pub selected: Vec<(u64, u32)>,
+}
+
+/// Reads lineups that were already entered into contests, in the bulk upload format written by `lineup_writer`.
+/// Columns named after the roster slots hold a player id, `Name (id)` or FanDuel's `id:Name`, the columns in front of
+/// them (i.e. DraftKings' Entry ID) are kept as the entry and any others are ignored
+pub fn read_entered_lineups(file_path: &str, roster_slots: &[RosterSlot], category_map: &HashMap<String, u32>, player_pool: &PlayerPool) -> Result<Vec<EnteredLineup>, String> {
+    let reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_path(file_path)
+        .map_err(|err| format!("failed to open entered lineups file {}: {}", file_path, err))?;
+    read_entered_rows(reader, roster_slots, category_map, player_pool)
+}
+
+fn read_entered_rows<R: Read>(mut reader: csv::Reader<R>, roster_slots: &[RosterSlot], category_map: &HashMap<String, u32>, player_pool: &PlayerPool) -> Result<Vec<EnteredLineup>, String> {
+    let headers: Vec<String> = reader.headers()
+        .map_err(|err| format!("error parsing entered lineups csv: {}", err))?
+        .iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+
+    // match each roster slot to the next unused column with its key
+    let mut columns: Vec<(usize, u32)> = Vec::new();
+    for slot in roster_slots {
+        let category = *category_map.get(&slot.key)
+            .ok_or_else(|| format!("no category found for roster slot {}", slot.key))?;
+        for _ in 0..slot.count {
+            let column = headers.iter().enumerate()
+                .position(|(i, header)| header == &slot.key && !columns.iter().any(|(c, _)| *c == i))
+                .ok_or_else(|| format!("entered lineups file is missing a {} column", slot.key))?;
+            columns.push((column, category));
+        }
+    }
+    let first_slot_column = columns.iter().map(|(column, _)| *column).min().unwrap_or(0);
+    let entry_columns: Vec<usize> = (0..first_slot_column).filter(|i| !headers[*i].is_empty()).collect();
+
+    let mut lineups: Vec<EnteredLineup> = Vec::new();
+    for result in reader.records() {
+        let record = result.map_err(|err| format!("error parsing entered lineups csv: {}", err))?;
+        let cells: Vec<&str> = columns.iter().map(|(column, _)| record.get(*column).unwrap_or("").trim()).collect();
+        if cells.iter().all(|cell| cell.is_empty()) {
+            continue;
+        }
+        let mut selected: Vec<(u64, u32)> = Vec::new();
+        for (cell, (_, category)) in cells.iter().zip(&columns) {
+            let entry_id = parse_entry_id(cell);
+            let player = player_pool.iter()
+                .map(|(_, player)| player)
+                .find(|p| (p.provider_id == entry_id || p.id.to_string() == entry_id) && p.categories.contains(category))
+                .ok_or_else(|| format!("entered player '{}' not found in the player pool", cell))?;
+            selected.push((player.id, *category));
+        }
+        let entry = entry_columns.iter()
+            .map(|i| (headers[*i].clone(), record.get(*i).unwrap_or("").trim().to_string()))
+            .collect();
+        lineups.push(EnteredLineup { entry, selected });
+    }
+    Ok(lineups)
+}
+
+/// Gets the player id out of an entered lineup cell, i.e. `13041692`, `Saquon Barkley (13041692)` or
+/// `37319-104449:Joe Horn Jr.`
+fn parse_entry_id(cell: &str) -> &str {
+    if let (Some(start), Some(end)) = (cell.rfind('('), cell.rfind(')')) {
+        if start < end {
+            return cell[start + 1..end].trim();
+        }
+    }
+    cell.split(':').next().unwrap_or("").trim()
+}
+
+/// Whether the player's game has started at `now`, players without a start time are never considered started
+pub fn has_started(player: &Player, now: &DateTime<FixedOffset>) -> bool {
+    player.start_time.is_some_and(|start_time| start_time <= *now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{ TimeZone };
+    use crate::test_helpers::{ get_test_slots };
+
+    #[test]
+    fn entry_ids() {
+        assert_eq!(parse_entry_id("13041692"), "13041692");
+        assert_eq!(parse_entry_id("Saquon Barkley (13041692)"), "13041692");
+        assert_eq!(parse_entry_id("37319-104449:Joe Horn Jr."), "37319-104449");
+    }
+
+    #[test]
+    fn read_rows() {
+        let (roster_slots, category_map) = get_test_slots();
+        let player_pool = get_test_pool();
+        let data = "Entry ID,Contest Name,QB,RB,FLEX,,Instructions\n\
+                    1,Milly Maker,Daniel Jones (3),1,2,,\n\
+                    ,,,,,,Upload your lineups\n";
+        let reader = csv::ReaderBuilder::new().flexible(true).from_reader(data.as_bytes());
+        let lineups = read_entered_rows(reader, &roster_slots, &category_map, &player_pool).unwrap();
+        assert_eq!(lineups, vec![EnteredLineup {
+            entry: vec![("Entry ID".to_string(), "1".to_string()), ("Contest Name".to_string(), "Milly Maker".to_string())],
+            selected: vec![(3, 1), (1, 2), (2, 5)],
+        }]);
+
+        let unknown = "QB,RB,FLEX\n3,1,99\n";
+        let reader = csv::ReaderBuilder::new().flexible(true).from_reader(unknown.as_bytes());
+        assert!(read_entered_rows(reader, &roster_slots, &category_map, &player_pool).is_err());
+    }
+
+    #[test]
+    fn started() {
+        // 2pm central is after the early games and before the late games in eastern time
+        let now = FixedOffset::west_opt(5 * 3600).unwrap().with_ymd_and_hms(2019, 9, 8, 14, 0, 0).unwrap();
+        let pool = get_test_pool();
+        assert!(has_started(pool.get_player(&1).unwrap(), &now));
+        assert!(!has_started(pool.get_player(&2).unwrap(), &now));
+        assert!(!has_started(pool.get_player(&3).unwrap(), &now));
+    }
+
+    fn get_test_pool() -> PlayerPool {
+        let eastern = FixedOffset::west_opt(4 * 3600).unwrap();
+        let early = eastern.with_ymd_and_hms(2019, 9, 8, 13, 0, 0).unwrap();
+        let late = eastern.with_ymd_and_hms(2019, 9, 8, 16, 25, 0).unwrap();
+        let players = vec![
+            Player { id: 1, provider_id: String::from("1"), categories: hashset!{2, 5}, start_time: Some(early), ..Default::default() },
+            Player { id: 2, provider_id: String::from("2"), categories: hashset!{2, 5}, start_time: Some(late), ..Default::default() },
+            Player { id: 3, provider_id: String::from("3"), categories: hashset!{1}, ..Default::default() },
+        ];
+        PlayerPool::new(players, false)
+    }
+}
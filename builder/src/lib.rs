@@ -11,10 +11,21 @@ pub mod builder;
 mod category_mapper;
 mod common;
 mod contest_reader;
+mod correlation;
+mod exposure;
+mod injury;
+mod late_swap;
 mod slate_reader;
 mod lineup_optimizer;
+mod lineup_writer;
 mod lp_optimizer;
+mod name_matcher;
 mod player_pool;
+mod projection_reader;
+mod randomizer;
+mod simulator;
+#[cfg(test)]
+mod test_helpers;
 
 #[cfg(test)]
 mod tests {
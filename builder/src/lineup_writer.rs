@@ -0,0 +1,135 @@
+use std::io::{ Write };
+use crate::builder::{ Lineup };
+use crate::common::{ Player, RosterSlot };
+
+/// Writes lineups in the DraftKings bulk upload format, one row per lineup with a column per roster slot
+/// (i.e. QB,RB,RB,WR,WR,WR,TE,FLEX,DST) containing the id of the player filling it. Lineups with an entry (late swap)
+/// have the entry columns in front of the slots, the DraftKings edit entries format
+pub fn write_draftkings_lineups(file_path: &str, lineups: &[Lineup], roster_slots: &[RosterSlot]) -> Result<(), String> {
+    let mut writer = csv::Writer::from_path(file_path)
+        .map_err(|err| format!("failed to create lineup file {}: {}", file_path, err))?;
+    write_rows(&mut writer, lineups, roster_slots, draftkings_entry)
+}
+
+/// Writes lineups in the FanDuel bulk upload format, one row per lineup with a column per roster slot
+/// containing the FanDuel id and name of the player filling it, i.e. `37319-104449:Joe Horn Jr.`
+pub fn write_fanduel_lineups(file_path: &str, lineups: &[Lineup], roster_slots: &[RosterSlot]) -> Result<(), String> {
+    let mut writer = csv::Writer::from_path(file_path)
+        .map_err(|err| format!("failed to create lineup file {}: {}", file_path, err))?;
+    write_rows(&mut writer, lineups, roster_slots, fanduel_entry)
+}
+
+fn draftkings_entry(player: &Player) -> String {
+    player.id.to_string()
+}
+
+fn fanduel_entry(player: &Player) -> String {
+    format!("{}:{}", player.provider_id, player.name)
+}
+
+fn write_rows<W: Write>(writer: &mut csv::Writer<W>, lineups: &[Lineup], roster_slots: &[RosterSlot], entry: fn(&Player) -> String) -> Result<(), String> {
+    let entry_headers: Vec<String> = lineups.first()
+        .map(|lineup| lineup.entry().iter().map(|(header, _)| header.clone()).collect())
+        .unwrap_or_default();
+    let mut headers = entry_headers.clone();
+    headers.extend(slot_headers(roster_slots));
+    writer.write_record(&headers)
+        .map_err(|err| format!("failed to write lineup file: {}", err))?;
+    for lineup in lineups {
+        let entry_values = entry_headers.iter().map(|header| {
+            lineup.entry().iter().find(|(h, _)| h == header).map(|(_, value)| value.clone()).unwrap_or_default()
+        });
+        let row: Vec<String> = entry_values.chain(lineup.slots().iter().map(|(_, player)| entry(player))).collect();
+        writer.write_record(&row)
+            .map_err(|err| format!("failed to write lineup file: {}", err))?;
+    }
+    writer.flush().map_err(|err| format!("failed to write lineup file: {}", err))
+}
+
+/// A column per roster slot, repeated for slots that hold more than one player
+fn slot_headers(roster_slots: &[RosterSlot]) -> Vec<String> {
+    roster_slots.iter()
+        .flat_map(|slot| std::iter::repeat_n(slot.key.clone(), slot.count as usize))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_draftkings_rows() {
+        let lineups = vec![
+            Lineup::new(vec![
+                (test_slot("QB"), test_player(12, "37319-12345", "Lamar Jackson")),
+                (test_slot("RB"), test_player(13041692, "37319-104449", "Mark Ingram II")),
+                (test_slot("FLEX"), test_player(13041144, "37319-55555", "Gus Edwards")),
+            ]),
+        ];
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        write_rows(&mut writer, &lineups, &get_test_slots(), draftkings_entry).unwrap();
+        let output = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert_eq!(output, "QB,RB,FLEX\n12,13041692,13041144\n");
+    }
+
+    #[test]
+    fn write_entry_rows() {
+        let entry = |id: &str| vec![("Entry ID".to_string(), id.to_string()), ("Contest Name".to_string(), "Milly Maker".to_string())];
+        let lineups = vec![
+            Lineup::new(vec![
+                (test_slot("QB"), test_player(12, "37319-12345", "Lamar Jackson")),
+                (test_slot("RB"), test_player(13041692, "37319-104449", "Mark Ingram II")),
+                (test_slot("FLEX"), test_player(13041144, "37319-55555", "Gus Edwards")),
+            ]).with_entry(entry("1")),
+            Lineup::new(vec![
+                (test_slot("QB"), test_player(12, "37319-12345", "Lamar Jackson")),
+                (test_slot("RB"), test_player(13041144, "37319-55555", "Gus Edwards")),
+                (test_slot("FLEX"), test_player(13041692, "37319-104449", "Mark Ingram II")),
+            ]).with_entry(entry("2")),
+        ];
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        write_rows(&mut writer, &lineups, &get_test_slots(), draftkings_entry).unwrap();
+        let output = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert_eq!(output, "Entry ID,Contest Name,QB,RB,FLEX\n1,Milly Maker,12,13041692,13041144\n2,Milly Maker,12,13041144,13041692\n");
+    }
+
+    #[test]
+    fn write_fanduel_rows() {
+        let lineups = vec![
+            Lineup::new(vec![
+                (test_slot("QB"), test_player(1, "37319-12345", "Lamar Jackson")),
+                (test_slot("RB"), test_player(2, "37319-104449", "Mark Ingram II")),
+                (test_slot("FLEX"), test_player(3, "37319-55555", "Gus Edwards")),
+            ]),
+        ];
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        write_rows(&mut writer, &lineups, &get_test_slots(), fanduel_entry).unwrap();
+        let output = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+        assert_eq!(output, "QB,RB,FLEX\n37319-12345:Lamar Jackson,37319-104449:Mark Ingram II,37319-55555:Gus Edwards\n");
+    }
+
+    fn get_test_slots() -> Vec<RosterSlot> {
+        vec![test_slot("QB"), test_slot("RB"), test_slot("FLEX")]
+    }
+
+    fn test_slot(key: &str) -> RosterSlot {
+        RosterSlot {
+            name: key.to_string(),
+            key: key.to_string(),
+            count: 1,
+            salary_multiplier: 1.0,
+            point_multiplier: 1.0,
+            any_position: false,
+            flex: false,
+        }
+    }
+
+    fn test_player(id: u64, provider_id: &str, name: &str) -> Player {
+        Player {
+            id,
+            provider_id: String::from(provider_id),
+            name: String::from(name),
+            ..Default::default()
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::{ HashSet, HashMap };
 use std::str::FromStr;
+use std::rc::{ Rc };
 
 use lp_modeler;
 use lp_modeler::problem::*;
@@ -7,17 +8,27 @@ use lp_modeler::problem::{LpObjective, Problem, LpProblem };
 use lp_modeler::operations::{LpOperations};
 use lp_modeler::variables::*;
 use lp_modeler::variables::LpExpression::*;
-use lp_modeler::solvers::{SolverTrait, CbcSolver};
+use lp_modeler::solvers::{SolverTrait, CbcSolver, Status};
 
-use crate::common::{ BuilderState, Player, RosterSlot };
+use crate::common;
+use crate::common::{ BuilderState, ObjectiveMode, Player, RosterSlot, StackRule, TeamLimit };
 use crate::player_pool::{ PlayerPool };
 
+const PLAYER_VAR_PREFIX: &str = "P_";
+const GAME_VAR_PREFIX: &str = "G_";
+
 pub struct LpOptimizer {
     player_pool: PlayerPool,
     problem: LpProblem,
 
     ///Key is (player_id, group_id) tuple
-    vars: HashMap<(u64, u32), LpBinary>
+    vars: HashMap<(u64, u32), LpBinary>,
+
+    /// Auxiliary variables which are set when at least one player from the game is selected, key is the game
+    game_vars: HashMap<String, LpBinary>,
+
+    /// Roster slots keyed by group id, used to apply the slot salary/point multipliers
+    slots: HashMap<u32, RosterSlot>,
 }
 
 impl LpOptimizer {
@@ -26,43 +37,52 @@ impl LpOptimizer {
             player_pool,
             problem: LpProblem::new("lp_optimizer", LpObjective::Maximize),
             vars: HashMap::new(),
+            game_vars: HashMap::new(),
+            slots: HashMap::new(),
         }
     }
 
     pub fn initialize(&mut self, builder_state: &BuilderState, mapped_indices: &HashMap<String, u32>) {
-        self.define_variables();
-        self.define_objective_fn();
         let roster_slots: Vec<RosterSlot> = match &builder_state.roster_slots {
             Some(ref rs) => rs.clone(),
             None => panic!("Catastrophic error, no roster slots to fill!")
         };
+        self.slots = common::map_roster_slots(&roster_slots, mapped_indices);
+        self.define_variables();
+        self.define_objective_fn(builder_state.objective_mode);
+        if let ObjectiveMode::OwnershipCap(max_ownership) = builder_state.objective_mode {
+            self.define_ownership_constraint(max_ownership);
+        }
         let salary_cap = &builder_state.salary_cap.unwrap();
-        self.define_constaints(&roster_slots, *salary_cap, mapped_indices);
+        self.define_constaints(&roster_slots, *salary_cap, builder_state.min_salary, mapped_indices);
+        self.define_lock_constraints(&builder_state.locked_players, &builder_state.excluded_players);
+        self.define_stack_constraints(&builder_state.stack_rules);
+        self.define_team_constraints(builder_state.max_per_team, &builder_state.team_limits);
+        if let Some(min_games) = builder_state.min_games {
+            self.define_game_constraints(min_games);
+        }
     }
 
-    pub fn solve(&self) -> Result<Vec<u64>, String> {
+    /// Solves the problem, returning the (player_id, group_id) of each selected player
+    pub fn solve(&self) -> Result<Vec<(u64, u32)>, String> {
         let solver = CbcSolver::new();
         // println!("{:?}", &self.problem);
         self.problem.write_lp("./debug_log/debug.lp");
         match solver.run(&self.problem) {
-            Ok((_, var_values)) => {
-                let mut player_ids: Vec<u64> = Vec::new();
+            Ok((status, var_values)) => {
+                if status != Status::Optimal && status != Status::SubOptimal {
+                    return Err(format!("Solver error: no feasible lineup ({:?})", status));
+                }
+                let mut selected: Vec<(u64, u32)> = Vec::new();
                 for (name, value) in var_values {
-                    if value == 1.0 {
-                        let id: u64 = match Self::id_from_variable_name(&name) {
-                            Ok(id) => id,
-                            Err(_) => {
-                                std::u64::MAX
-                            }
-                        };
-                        if id != std::u64::MAX {
-                            player_ids.push(id);
-                        } else {
-                            return Err("failed to parse result id".to_string());
+                    if value == 1.0 && name.starts_with(PLAYER_VAR_PREFIX) {
+                        match Self::id_from_variable_name(&name) {
+                            Ok(id_and_group) => selected.push(id_and_group),
+                            Err(_) => return Err("failed to parse result id".to_string()),
                         }
                     }
                 }
-                return Ok(player_ids);
+                return Ok(selected);
             },
             Err(err) => {
                 return Err(format!("Solver error: {}", err));
@@ -70,28 +90,120 @@ impl LpOptimizer {
         }
     }
 
+    /// Forces any further solution to differ from the solved lineup `selected` by at least `min_unique` players
+    pub fn exclude_lineup(&mut self, selected: &[(u64, u32)], min_unique: u32) {
+        let min_unique = std::cmp::max(min_unique, 1) as usize;
+        let max_shared = selected.len().saturating_sub(min_unique);
+        // a plain no-good cut counts another MVP/captain as a new lineup, but not moving players between RB and FLEX
+        let cut: Vec<LpExpression> = if min_unique == 1 {
+            selected.iter().flat_map(|(id, group_id)| self.same_multiplier_vars(*id, *group_id)).collect()
+        } else {
+            let player_ids: Vec<u64> = selected.iter().map(|(id, _)| *id).collect();
+            self.player_vars(&player_ids)
+        };
+        self.problem += chained_sum(&cut).le(max_shared as f32);
+    }
+
+    /// Forces exactly one of `player_ids` into every further solution.
+    /// Multiple ids are used when the same player has more than one entry in the pool
+    pub fn lock_players(&mut self, player_ids: &[u64]) {
+        let vars = self.player_vars(player_ids);
+        if vars.is_empty() {
+            return;
+        }
+        self.problem += chained_sum(&vars).equal(1);
+    }
+
+    /// Forces a player into the roster slot group `group_id` in every further solution, i.e. a player whose game
+    /// has already started during late swap
+    pub fn lock_player_slot(&mut self, player_id: u64, group_id: u32) -> Result<(), String> {
+        let var = self.vars.get(&(player_id, group_id))
+            .ok_or_else(|| format!("player {} can not be locked into group {}", player_id, group_id))?;
+        self.problem += chained_sum(&[1.0 * var]).equal(1);
+        Ok(())
+    }
+
+    /// Prevents all of `player_ids` from being selected in any further solution
+    pub fn exclude_players(&mut self, player_ids: &[u64]) {
+        let vars = self.player_vars(player_ids);
+        if vars.is_empty() {
+            return;
+        }
+        self.problem += chained_sum(&vars).equal(0);
+    }
+
+    fn player_vars(&self, player_ids: &[u64]) -> Vec<LpExpression> {
+        let mut vars: Vec<LpExpression> = Vec::new();
+        for ((id, _), var) in &self.vars {
+            if player_ids.contains(id) {
+                vars.push(1.0 * var);
+            }
+        }
+        vars
+    }
+
+    /// The player's variables for every slot with the same multipliers as `group_id`
+    fn same_multiplier_vars(&self, player_id: u64, group_id: u32) -> Vec<LpExpression> {
+        let multipliers = (self.salary_multiplier(group_id), self.point_multiplier(group_id));
+        self.vars.iter()
+            .filter(|((id, group), _)| *id == player_id && (self.salary_multiplier(*group), self.point_multiplier(*group)) == multipliers)
+            .map(|(_, var)| 1.0 * var)
+            .collect()
+    }
+
     fn define_variables(&mut self) {
         // TODO: try using a different variable for each category for every player
         //      example: Saquon Barkley needs a saquon_rb variable and a saquon_flex variable
+        // only categories that fill a roster slot get a variable, any others (i.e. a player's natural position when
+        // every slot is any_position) would be unconstrained by the roster
         for player in self.player_pool.get_all() {
             for category in player.categories{
+                if !self.slots.contains_key(&category) {
+                    continue;
+                }
                 let var_name = Self::create_variable_name(player.id, category);
                 self.vars.insert((player.id, category), LpBinary::new(&var_name));
             }
         }
     }
 
-    fn define_objective_fn(&mut self) {
+    fn define_objective_fn(&mut self, objective_mode: ObjectiveMode) {
+        let ownership_penalty = match objective_mode {
+            ObjectiveMode::OwnershipPenalty(lambda) => lambda,
+            _ => 0.0,
+        };
         let mut obj_vec: Vec<LpExpression> = Vec::new();
+        for (&(id, group_id), var) in &self.vars {
+            let player = self.player_pool.get_player(&id).unwrap();
+            let obj_coef = (player.projected_points * self.point_multiplier(group_id) - ownership_penalty * player.ownership) as f32;
+            obj_vec.push(obj_coef * var);
+        }
+        self.problem += chained_sum(&obj_vec);
+    }
+
+    /// The total projected ownership of the lineup must not exceed `max_ownership`
+    fn define_ownership_constraint(&mut self, max_ownership: f64) {
+        let mut ownership_vec: Vec<LpExpression> = Vec::new();
         for (&(id, _), var) in &self.vars {
-            let obj_coef: Vec<f32> = self.player_pool.iter()
-                    .filter(|(i,_)| **i == id).map(|(_,p)| p.projected_points as f32).take(1).collect();
-            obj_vec.push(obj_coef[0] * var);
+            let ownership = self.player_pool.get_player(&id).unwrap().ownership as f32;
+            if ownership > 0.0 {
+                ownership_vec.push(ownership * var);
+            }
+        }
+        if !ownership_vec.is_empty() {
+            self.problem += chained_sum(&ownership_vec).le(max_ownership as f32);
         }
-        self.problem += lp_sum(&obj_vec);
     }
 
-    fn define_constaints(&mut self, roster_slots: &[RosterSlot], salary_cap: u32, mapped_indices: &HashMap<String, u32>) {
+    fn point_multiplier(&self, group_id: u32) -> f64 {
+        self.slots.get(&group_id).map(|s| s.point_multiplier).unwrap_or(1.0)
+    }
+
+    fn salary_multiplier(&self, group_id: u32) -> f64 {
+        self.slots.get(&group_id).map(|s| s.salary_multiplier).unwrap_or(1.0)
+    }
+
+    fn define_constaints(&mut self, roster_slots: &[RosterSlot], salary_cap: u32, min_salary: Option<u32>, mapped_indices: &HashMap<String, u32>) {
         // Constraint 1: each position group must contain exactly N items (as specified by the constest template)
         for slot in roster_slots {
             // println!("mapping slot:\n{:?}", slot);
@@ -107,72 +219,193 @@ impl LpOptimizer {
             }
             // println!("\nslot constraint:\n\n{:?}", group_constraint);
             // Into<LpExpression> is only implemented for f32 and i32 integer types. these values are represtative of real world positions on teams so they will be low enough that this conversion should never become an issue
-            self.problem += lp_sum(&group_constraint).equal(slot_count as i32);
+            self.problem += chained_sum(&group_constraint).equal(slot_count as i32);
         }
 
         // Constraint 2: salaries of each player cannot exceed salary cap
         let mut cons_vec: Vec<LpExpression> = Vec::new();
-        for ((id, _), var) in &self.vars {
-            let salary_coef: f32 = self.player_pool.get_player(id).unwrap().price as f32;
+        for ((id, group_id), var) in &self.vars {
+            let price = self.player_pool.get_player(id).unwrap().price as f64;
+            let salary_coef = (price * self.salary_multiplier(*group_id)) as f32;
             cons_vec.push(salary_coef * var);
         }
-        self.problem += lp_sum(&cons_vec).le(salary_cap as i32);
+        self.problem += chained_sum(&cons_vec).le(salary_cap as i32);
+
+        // Constraint 2b: salaries of each player must meet the salary floor
+        if let Some(min_salary) = min_salary {
+            self.problem += chained_sum(&cons_vec).ge(min_salary as i32);
+        }
 
         // Constraint 3: a player can only be included once so each category variation must add to 1
         for (id, player) in self.player_pool.iter() {
-            let mut duplication_constraint: Vec<LpExpression> = Vec::new();
-            if player.categories.len() > 1 {
-                for cat in &player.categories {
-                    let var = self.vars.get(&(*id, *cat)).unwrap();
-                    duplication_constraint.push(1.0 * var);
-                }
-                self.problem += lp_sum(&duplication_constraint).le(1.0);
+            let duplication_constraint: Vec<LpExpression> = player.categories.iter()
+                .filter_map(|cat| self.vars.get(&(*id, *cat)))
+                .map(|var| 1.0 * var)
+                .collect();
+            if duplication_constraint.len() > 1 {
+                self.problem += chained_sum(&duplication_constraint).le(1.0);
             }
         }
 
-        for (id, player) in self.player_pool.iter() {
-            if let Some(same_name) = self.player_pool.get_players_by_name(&player.name) {
-                for possibly_same in same_name {
-                    if possibly_same.id != player.id &&
-                       possibly_same.team == player.team &&
-                       possibly_same.position == player.position { // if the ids are different but the name, team, and position are the same then we need to add a constraint
-                        let mut duplication_constraint: Vec<LpExpression> = Vec::new();
-                        for player_cat in &player.categories {
-                            let player_var = self.vars.get(&(*id, *player_cat)).unwrap();
-                            duplication_constraint.push(1.0 * player_var);
-                            for duplicate_cat in &possibly_same.categories {
-                                let dup_var = self.vars.get(&(possibly_same.id, *duplicate_cat)).unwrap();
-                                duplication_constraint.push(1.0 * dup_var);
-                            }
-                        }
-                        self.problem += lp_sum(&duplication_constraint).le(1.0);
+        self.define_showdown_constraints();
+    }
+
+    fn define_lock_constraints(&mut self, locked_players: &[Vec<u64>], excluded_players: &[u64]) {
+        // Constraint 4: locked players must be in the lineup
+        for player_ids in locked_players {
+            self.lock_players(player_ids);
+        }
+
+        // Constraint 5: excluded players can not be in the lineup
+        self.exclude_players(excluded_players);
+    }
+
+    fn define_stack_constraints(&mut self, stack_rules: &[StackRule]) {
+        // Constraint 6: selecting a player that triggers a stack requires N teammates (and optionally M opponents)
+        // from the stack positions: sum(teammates) - N * trigger >= 0
+        for rule in stack_rules {
+            for trigger in self.player_pool.get_all() {
+                if !trigger.has_position(&rule.positions) {
+                    continue;
+                }
+                let trigger_ids = [trigger.id];
+                if rule.count > 0 {
+                    let mut stack_constraint = self.team_vars(&rule.stack_positions, &trigger.team, Some(trigger.id));
+                    for var in self.player_vars(&trigger_ids) {
+                        stack_constraint.push(-(rule.count as f32) * var);
+                    }
+                    self.problem += chained_sum(&stack_constraint).ge(0);
+                }
+                if rule.bring_back_count > 0 {
+                    let mut bring_back_constraint = self.team_vars(&rule.bring_back_positions, &trigger.opponent, Some(trigger.id));
+                    for var in self.player_vars(&trigger_ids) {
+                        bring_back_constraint.push(-(rule.bring_back_count as f32) * var);
                     }
+                    self.problem += chained_sum(&bring_back_constraint).ge(0);
                 }
             }
         }
     }
 
+    /// Gets the variables of every player on `team` at one of `positions`, other than `exclude_id`
+    fn team_vars(&self, positions: &[String], team: &str, exclude_id: Option<u64>) -> Vec<LpExpression> {
+        let ids: Vec<u64> = self.player_pool.iter()
+            .filter(|(id, p)| Some(**id) != exclude_id && p.team == team && p.has_position(positions))
+            .map(|(id, _)| *id)
+            .collect();
+        self.player_vars(&ids)
+    }
+
+    fn define_team_constraints(&mut self, max_per_team: Option<u32>, team_limits: &[TeamLimit]) {
+        let mut teams: Vec<String> = self.player_pool.iter().map(|(_, p)| p.team.clone()).collect();
+        teams.sort();
+        teams.dedup();
+
+        for team in &teams {
+            // Constraint 7: no more than N players from a single team
+            if let Some(max) = max_per_team {
+                let ids: Vec<u64> = self.player_pool.iter()
+                    .filter(|(_, p)| &p.team == team)
+                    .map(|(id, _)| *id)
+                    .collect();
+                let team_constraint = self.player_vars(&ids);
+                self.problem += chained_sum(&team_constraint).le(max as i32);
+            }
+
+            // Constraint 8: no more than N players from a single team at the given positions
+            for limit in team_limits {
+                let team_constraint = self.team_vars(&limit.positions, team, None);
+                if !team_constraint.is_empty() {
+                    self.problem += chained_sum(&team_constraint).le(limit.max as i32);
+                }
+            }
+        }
+    }
+
+    fn define_game_constraints(&mut self, min_games: u32) {
+        // players without game info (i.e. a postponed game) can't count towards the minimum
+        let mut games: Vec<String> = self.player_pool.iter()
+            .map(|(_, p)| p.game.clone())
+            .filter(|game| !game.is_empty())
+            .collect();
+        games.sort();
+        games.dedup();
+
+        // Constraint 9: a game can only count towards the minimum if a player from that game is selected
+        for (index, game) in games.iter().enumerate() {
+            let game_var = LpBinary::new(&format!("{}{}", GAME_VAR_PREFIX, index));
+            let ids: Vec<u64> = self.player_pool.iter()
+                .filter(|(_, p)| &p.game == game)
+                .map(|(id, _)| *id)
+                .collect();
+            let mut game_constraint = self.player_vars(&ids);
+            game_constraint.push(-1.0 * &game_var);
+            self.problem += chained_sum(&game_constraint).ge(0);
+            self.game_vars.insert(game.clone(), game_var);
+        }
+
+        // Constraint 10: the lineup must contain players from at least N games
+        let game_count: Vec<LpExpression> = self.game_vars.values().map(|var| 1.0 * var).collect();
+        self.problem += chained_sum(&game_count).ge(min_games as i32);
+    }
+
+    /// Showdown style slates (i.e. DK captain mode) list the same player once per slot with a different id. A player
+    /// may occupy the CPT slot or a FLEX slot but not both, so every entry for the same player must add to 1.
+    /// Slots that share an entry (i.e. FanDuel MVP) are already covered by constraint 3
     fn define_showdown_constraints(&mut self) {
-        // TODO
+        let mut handled: HashSet<u64> = HashSet::new();
+        for player in self.player_pool.get_all() {
+            if handled.contains(&player.id) {
+                continue;
+            }
+            let entries: Vec<u64> = match self.player_pool.get_players_by_name(&player.name) {
+                Some(same_name) => same_name.iter()
+                    .filter(|p| p.identity_key() == player.identity_key())
+                    .map(|p| p.id)
+                    .collect(),
+                None => continue,
+            };
+            handled.extend(entries.iter());
+            if entries.len() > 1 {
+                let duplication_constraint = self.player_vars(&entries);
+                self.problem += chained_sum(&duplication_constraint).le(1.0);
+            }
+        }
     }
 
     fn create_variable_name(player_id: u64, group_id: u32) -> String {
-        format!("P_{}_{}", group_id, player_id)
+        format!("{}{}_{}", PLAYER_VAR_PREFIX, group_id, player_id)
     }
 
-    fn id_from_variable_name(variable_name: &str) -> Result<u64, String> {
+    fn id_from_variable_name(variable_name: &str) -> Result<(u64, u32), String> {
         let split: Vec<&str> = variable_name.split('_').collect();
-        match split[2].parse::<u64>() {
-            Ok(id) => Ok(id),
-            Err(err) => Err(format!("failed to parse variable name to id: {}", variable_name))
+        if split.len() != 3 {
+            return Err(format!("failed to parse variable name to id: {}", variable_name));
+        }
+        match (split[2].parse::<u64>(), split[1].parse::<u32>()) {
+            (Ok(id), Ok(group_id)) => Ok((id, group_id)),
+            _ => Err(format!("failed to parse variable name to id: {}", variable_name))
         }
     }
 }
 
+/// Sums the expressions left to right, i.e. `((a + b) + c) + d`, which is the form lp_modeler simplifies sums into.
+/// `lp_sum` nests them the other way and simplifying that rebuilds the whole sum once per term, overflowing the
+/// stack on full slates
+fn chained_sum(exprs: &[LpExpression]) -> LpExpression {
+    let mut iter = exprs.iter();
+    match iter.next() {
+        Some(first) => iter.fold(first.clone(), |sum, expr| AddExpr(Rc::new(sum), Rc::new(expr.clone()))),
+        None => EmptyExpr,
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::category_mapper;
+    use crate::contest_reader::{ load_contest };
+    use crate::slate_reader::{ append_any_position_categories, read_slate };
 
     #[test]
     fn test_lp_optimizer() {
@@ -183,6 +416,7 @@ mod tests {
             player_data_list: None,
             roster_slots: Some(get_test_roster_slots()),
             salary_cap: Some(16300),
+            ..Default::default()
         };
         let mapped_indices = hashmap!{
             "QB".to_string() => 1,
@@ -193,8 +427,8 @@ mod tests {
         let mut optimizer: LpOptimizer = LpOptimizer::new(player_pool.clone());
         optimizer.initialize(&builder_state, &mapped_indices);
         match optimizer.solve() {
-            Ok(ids) => {
-                for id in ids {
+            Ok(selected) => {
+                for (id, _) in selected {
                     let player = player_pool.get_player(&id).unwrap();
                     println!("{:?}", player);
                 }
@@ -207,6 +441,372 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_lp_optimizer_exclude_lineup() {
+        let (mut optimizer, _) = get_test_optimizer(get_test_players(), BuilderState::default());
+        let first = optimizer.solve().expect("first solve failed");
+        optimizer.exclude_lineup(&first, 1);
+        let mut first = player_ids(first);
+        let mut second = player_ids(optimizer.solve().expect("second solve failed"));
+        first.sort();
+        second.sort();
+        assert_eq!(second.len(), 3);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_lp_optimizer_exclude_lineup_multiplier_slots() {
+        let players: Vec<Player> = [("Davante Adams", 20.0), ("Aaron Rodgers", 15.0)].iter().enumerate().map(|(i, (name, points))| Player {
+            id: i as u64,
+            name: name.to_string(),
+            team: String::from("GB"),
+            categories: hashset!{10, 11},
+            price: 5000,
+            projected_points: *points,
+            ..Default::default()
+        }).collect();
+        let player_pool = PlayerPool::new(players, true);
+        let slot = |key: &str, multiplier: f64| RosterSlot {
+            name: key.to_string(),
+            key: key.to_string(),
+            count: 1,
+            salary_multiplier: 1.0,
+            point_multiplier: multiplier,
+            any_position: true,
+            flex: false,
+        };
+        let builder_state = BuilderState {
+            player_pool: Some(player_pool.clone()),
+            roster_slots: Some(vec![slot("MVP", 1.5), slot("FLEX", 1.0)]),
+            salary_cap: Some(60000),
+            ..Default::default()
+        };
+        let mapped_indices = hashmap!{
+            "MVP".to_string() => 10,
+            "FLEX".to_string() => 11,
+        };
+
+        let mut optimizer: LpOptimizer = LpOptimizer::new(player_pool.clone());
+        optimizer.initialize(&builder_state, &mapped_indices);
+        let mut first = optimizer.solve().expect("first solve failed");
+        first.sort();
+        assert_eq!(first, vec![(0, 10), (1, 11)]);
+        optimizer.exclude_lineup(&first, 1);
+        // the same players with a different MVP is a different lineup
+        let mut second = optimizer.solve().expect("second solve failed");
+        second.sort();
+        assert_eq!(second, vec![(0, 11), (1, 10)]);
+        optimizer.exclude_lineup(&second, 1);
+        assert!(optimizer.solve().is_err());
+    }
+
+    #[test]
+    fn test_lp_optimizer_min_unique() {
+        let (mut optimizer, _) = get_test_optimizer(get_test_players(), BuilderState::default());
+        let first = optimizer.solve().expect("first solve failed");
+        optimizer.exclude_lineup(&first, 2);
+        let first = player_ids(first);
+        let second = player_ids(optimizer.solve().expect("second solve failed"));
+        let shared = second.iter().filter(|id| first.contains(id)).count();
+        assert!(shared <= 1, "lineups share {} players", shared);
+    }
+
+    #[test]
+    fn test_lp_optimizer_lock_exclude() {
+        let builder_state = BuilderState {
+            locked_players: vec![vec![0]],
+            excluded_players: vec![4],
+            ..Default::default()
+        };
+        let (optimizer, _) = get_test_optimizer(get_test_players(), builder_state);
+        let ids = player_ids(optimizer.solve().expect("solve failed"));
+        assert!(ids.contains(&0), "locked player missing from lineup");
+        assert!(!ids.contains(&4), "excluded player in lineup");
+    }
+
+    #[test]
+    fn test_lp_optimizer_lock_slot() {
+        let (mut optimizer, _) = get_test_optimizer(get_test_players(), BuilderState::default());
+        optimizer.lock_player_slot(2, 2).unwrap();
+        assert!(optimizer.lock_player_slot(2, 3).is_err());
+        let selected = optimizer.solve().expect("solve failed");
+        assert!(selected.contains(&(2, 2)), "locked player missing from their slot");
+    }
+
+    #[test]
+    fn test_lp_optimizer_stack() {
+        let mut players = get_test_players();
+        players.push(Player {
+            id: 8,
+            name: String::from("Julian Edelman"),
+            team: String::from("NE"),
+            position: String::from("WR"),
+            categories: hashset!{3},
+            price: 4000,
+            projected_points: 12.0,
+            ..Default::default()
+        });
+        let builder_state = BuilderState {
+            stack_rules: vec![StackRule {
+                positions: vec!["QB".to_string()],
+                stack_positions: vec!["WR".to_string()],
+                count: 1,
+                bring_back_positions: Vec::new(),
+                bring_back_count: 0,
+            }],
+            ..Default::default()
+        };
+        let (optimizer, _) = get_test_optimizer(players, builder_state);
+        let ids = player_ids(optimizer.solve().expect("solve failed"));
+        // only New England has a QB and WR to stack
+        assert!(ids.contains(&0));
+        assert!(ids.contains(&8));
+    }
+
+    #[test]
+    fn test_lp_optimizer_max_per_team() {
+        let mut players = get_test_players();
+        for player in players.iter_mut() {
+            if player.id == 5 {
+                player.team = String::from("NYG");
+            }
+        }
+        let builder_state = BuilderState { max_per_team: Some(1), ..Default::default() };
+        let (optimizer, player_pool) = get_test_optimizer(players, builder_state);
+        let ids = player_ids(optimizer.solve().expect("solve failed"));
+        let mut teams: Vec<String> = ids.iter().map(|id| player_pool.get_player(id).unwrap().team.clone()).collect();
+        teams.sort();
+        teams.dedup();
+        assert_eq!(teams.len(), 3);
+    }
+
+    #[test]
+    fn test_lp_optimizer_min_games() {
+        let mut players = get_test_players();
+        for player in players.iter_mut() {
+            player.game = if player.id == 1 || player.id == 4 || player.id == 5 {
+                String::from("G1")
+            } else {
+                String::from("G2")
+            };
+        }
+        let builder_state = BuilderState { min_games: Some(2), ..Default::default() };
+        let (optimizer, player_pool) = get_test_optimizer(players, builder_state);
+        let ids = player_ids(optimizer.solve().expect("solve failed"));
+        assert_eq!(ids.len(), 3);
+        let mut games: Vec<String> = ids.iter().map(|id| player_pool.get_player(id).unwrap().game.clone()).collect();
+        games.sort();
+        games.dedup();
+        assert_eq!(games.len(), 2);
+    }
+
+    #[test]
+    fn test_lp_optimizer_min_games_without_game_info() {
+        let mut players = get_test_players();
+        for player in players.iter_mut() {
+            if player.id == 1 || player.id == 4 || player.id == 5 {
+                player.game = String::from("G1");
+            }
+        }
+        // the players without a game don't make up a second game
+        let builder_state = BuilderState { min_games: Some(2), ..Default::default() };
+        let (optimizer, _) = get_test_optimizer(players, builder_state);
+        assert!(optimizer.solve().is_err());
+    }
+
+    #[test]
+    fn test_lp_optimizer_showdown() {
+        let mut players = Vec::new();
+        for (i, (name, price, points)) in [("Davante Adams", 10000, 20.0), ("Aaron Rodgers", 8000, 15.0), ("Allen Robinson", 5000, 10.0)].iter().enumerate() {
+            let id = (i as u64) * 2;
+            for (offset, category) in [(0, 7), (1, 4)].iter() {
+                players.push(Player {
+                    id: id + offset,
+                    name: name.to_string(),
+                    team: String::from("GB"),
+                    position: String::from("WR"),
+                    categories: hashset!{*category},
+                    price: *price,
+                    projected_points: *points,
+                    ..Default::default()
+                });
+            }
+        }
+        let player_pool = PlayerPool::new(players, true);
+        let builder_state = BuilderState {
+            player_pool: Some(player_pool.clone()),
+            roster_slots: Some(get_test_roster_slots_showdown()),
+            salary_cap: Some(40000),
+            ..Default::default()
+        };
+        let mapped_indices = hashmap!{
+            "FLEX".to_string() => 4,
+            "CPT".to_string() => 7,
+        };
+
+        let mut optimizer: LpOptimizer = LpOptimizer::new(player_pool.clone());
+        optimizer.initialize(&builder_state, &mapped_indices);
+        let mut selected = optimizer.solve().expect("solve failed");
+        selected.sort();
+        // Davante at CPT, the other two at FLEX. Davante can't also fill a FLEX slot
+        assert_eq!(selected, vec![(0, 7), (3, 4), (5, 4)]);
+    }
+
+    #[test]
+    fn test_lp_optimizer_single_game_slate() {
+        let mut category_map = category_mapper::map_categories("nfl").unwrap();
+        let mut builder_state = BuilderState::default();
+        load_contest("../resources/game_templates/fanduel/nfl/single_game.json", &mut builder_state);
+        category_mapper::append_slot_categories(&mut category_map, builder_state.roster_slots.as_ref().unwrap());
+        read_slate("../data/fd_nfl_full.csv", "fanduel", &mut builder_state, &category_map).unwrap();
+        let players: Vec<Player> = builder_state.player_pool.as_ref().unwrap().get_all().into_iter()
+            .filter(|p| p.game == "ATL@MIA")
+            .collect();
+        let player_pool = PlayerPool::new(players, true);
+        builder_state.player_pool = Some(player_pool.clone());
+
+        let mut optimizer: LpOptimizer = LpOptimizer::new(player_pool.clone());
+        optimizer.initialize(&builder_state, &category_map);
+        // players keep their natural positions (QB, RB, WR...) but only the MVP and FLEX slots get variables
+        let mvp = category_map["MVP"];
+        let flex = category_map["FLEX"];
+        assert!(optimizer.vars.keys().all(|(_, group_id)| *group_id == mvp || *group_id == flex));
+        assert_eq!(optimizer.vars.len(), player_pool.get_all().len() * 2);
+
+        let selected = optimizer.solve().expect("solve failed");
+        assert_eq!(selected.iter().filter(|(_, group_id)| *group_id == mvp).count(), 1);
+        assert_eq!(selected.iter().filter(|(_, group_id)| *group_id == flex).count(), 4);
+    }
+
+    #[test]
+    fn test_lp_optimizer_nba_single_game() {
+        let mut category_map = category_mapper::map_categories("nba").unwrap();
+        let mut builder_state = BuilderState::default();
+        load_contest("../resources/game_templates/fanduel/nba/single_game.json", &mut builder_state);
+        category_mapper::append_slot_categories(&mut category_map, builder_state.roster_slots.as_ref().unwrap());
+        let slots = common::map_roster_slots(builder_state.roster_slots.as_ref().unwrap(), &category_map);
+        let positions = [("PG", 40.0), ("SG", 35.0), ("SF", 30.0), ("PF", 25.0), ("C", 20.0), ("C", 10.0)];
+        let players: Vec<Player> = positions.iter().enumerate().map(|(i, (position, projected_points))| {
+            // the categories the FanDuel slate reader gives a player, their position plus every any_position slot
+            let mut categories = hashset!{category_map[*position]};
+            append_any_position_categories(&mut categories, &slots);
+            Player {
+                id: i as u64,
+                position: position.to_string(),
+                categories,
+                price: 10000,
+                projected_points: *projected_points,
+                ..Default::default()
+            }
+        }).collect();
+        let player_pool = PlayerPool::new(players, true);
+        builder_state.player_pool = Some(player_pool.clone());
+
+        let mut optimizer: LpOptimizer = LpOptimizer::new(player_pool.clone());
+        optimizer.initialize(&builder_state, &category_map);
+        // PG, SG, SF, PF and C aren't roster slots, only MVP, STAR, PRO and UTIL get variables
+        assert!(optimizer.vars.keys().all(|(_, group_id)| slots.contains_key(group_id)));
+        assert_eq!(optimizer.vars.len(), 6 * 4);
+
+        let mut selected = optimizer.solve().expect("solve failed");
+        selected.sort();
+        let (mvp, star, pro, util) = (category_map["MVP"], category_map["STAR"], category_map["PRO"], category_map["UTIL"]);
+        assert_eq!(selected, vec![(0, mvp), (1, star), (2, pro), (3, util), (4, util)]);
+    }
+
+    #[test]
+    fn test_lp_optimizer_multiplier_slots() {
+        let mut players = get_test_players();
+        for player in players.iter_mut() {
+            player.categories = hashset!{10, 11, 12};
+        }
+        let player_pool = PlayerPool::new(players, true);
+        let slot = |key: &str, multiplier: f64| RosterSlot {
+            name: key.to_string(),
+            key: key.to_string(),
+            count: 1,
+            salary_multiplier: 1.0,
+            point_multiplier: multiplier,
+            any_position: true,
+            flex: false,
+        };
+        let builder_state = BuilderState {
+            player_pool: Some(player_pool.clone()),
+            roster_slots: Some(vec![slot("MVP", 2.0), slot("STAR", 1.5), slot("PRO", 1.2)]),
+            salary_cap: Some(60000),
+            ..Default::default()
+        };
+        let mapped_indices = hashmap!{
+            "MVP".to_string() => 10,
+            "STAR".to_string() => 11,
+            "PRO".to_string() => 12,
+        };
+
+        let mut optimizer: LpOptimizer = LpOptimizer::new(player_pool.clone());
+        optimizer.initialize(&builder_state, &mapped_indices);
+        let mut selected = optimizer.solve().expect("solve failed");
+        selected.sort();
+        // the highest projected players fill the slots with the largest multipliers
+        assert_eq!(selected, vec![(1, 11), (2, 12), (4, 10)]);
+    }
+
+    #[test]
+    fn test_lp_optimizer_min_salary() {
+        let mut players = get_test_players();
+        players.push(Player {
+            id: 8,
+            name: String::from("Cheap Receiver"),
+            team: String::from("NE"),
+            position: String::from("WR"),
+            categories: hashset!{3},
+            price: 3000,
+            projected_points: 25.0,
+            ..Default::default()
+        });
+        let builder_state = BuilderState {
+            salary_cap: Some(20000),
+            min_salary: Some(18000),
+            ..Default::default()
+        };
+        let (optimizer, player_pool) = get_test_optimizer(players, builder_state);
+        let ids = player_ids(optimizer.solve().expect("solve failed"));
+        let salary: u32 = ids.iter().map(|id| player_pool.get_player(id).unwrap().price).sum();
+        assert!(salary >= 18000, "salary floor not met: {}", salary);
+        assert!(!ids.contains(&8));
+    }
+
+    #[test]
+    fn test_lp_optimizer_ownership_penalty() {
+        let builder_state = BuilderState { salary_cap: Some(30000), ..Default::default() };
+        let (optimizer, _) = get_test_optimizer(get_test_players_with_ownership(), builder_state);
+        let ids = player_ids(optimizer.solve().expect("solve failed"));
+        assert!(ids.contains(&4));
+
+        let builder_state = BuilderState {
+            salary_cap: Some(30000),
+            objective_mode: ObjectiveMode::OwnershipPenalty(0.5),
+            ..Default::default()
+        };
+        let (optimizer, _) = get_test_optimizer(get_test_players_with_ownership(), builder_state);
+        let ids = player_ids(optimizer.solve().expect("solve failed"));
+        assert!(!ids.contains(&4));
+        assert!(ids.contains(&2));
+    }
+
+    #[test]
+    fn test_lp_optimizer_ownership_cap() {
+        let builder_state = BuilderState {
+            salary_cap: Some(30000),
+            objective_mode: ObjectiveMode::OwnershipCap(50.0),
+            ..Default::default()
+        };
+        let (optimizer, player_pool) = get_test_optimizer(get_test_players_with_ownership(), builder_state);
+        let ids = player_ids(optimizer.solve().expect("solve failed"));
+        let ownership: f64 = ids.iter().map(|id| player_pool.get_player(id).unwrap().ownership).sum();
+        assert!(ownership <= 50.0, "ownership cap exceeded: {}", ownership);
+        assert!(!(ids.contains(&1) && ids.contains(&4)));
+    }
+
     // #[test]
     fn test_lp_optimizer_flex() {
         let players = get_test_players_multi_category();
@@ -216,6 +816,7 @@ mod tests {
             player_data_list: None,
             roster_slots: Some(get_test_roster_slots_flex()),
             salary_cap: Some(12500),
+            ..Default::default()
         };
         let mapped_indices = hashmap!{
             "QB".to_string() => 1,
@@ -227,8 +828,8 @@ mod tests {
         let mut optimizer: LpOptimizer = LpOptimizer::new(player_pool.clone());
         optimizer.initialize(&builder_state, &mapped_indices);
         match optimizer.solve() {
-            Ok(ids) => {
-                for id in ids {
+            Ok(selected) => {
+                for (id, _) in selected {
                     let player = player_pool.get_player(&id).unwrap();
                     println!("{:?}", player);
                 }
@@ -477,6 +1078,28 @@ mod tests {
         }
     }
 
+    /// Initializes an optimizer over `players` with the QB/RB/WR test slots and a salary cap of 16300 unless
+    /// `builder_state` sets one
+    fn get_test_optimizer(players: Vec<Player>, mut builder_state: BuilderState) -> (LpOptimizer, PlayerPool) {
+        let player_pool = PlayerPool::new(players, true);
+        builder_state.player_pool = Some(player_pool.clone());
+        builder_state.roster_slots = Some(get_test_roster_slots());
+        builder_state.salary_cap.get_or_insert(16300);
+        let mapped_indices = hashmap!{
+            "QB".to_string() => 1,
+            "RB".to_string() => 2,
+            "WR".to_string() => 3,
+        };
+
+        let mut optimizer: LpOptimizer = LpOptimizer::new(player_pool.clone());
+        optimizer.initialize(&builder_state, &mapped_indices);
+        (optimizer, player_pool)
+    }
+
+    fn player_ids(selected: Vec<(u64, u32)>) -> Vec<u64> {
+        selected.into_iter().map(|(id, _)| id).collect()
+    }
+
     fn get_test_players() -> Vec<Player> {
         let mut players = Vec::new();
         players.push(Player {
@@ -487,6 +1110,7 @@ mod tests {
             categories: hashset!{1},
             price: 4500,
             projected_points: 18.4,
+            ..Default::default()
         });
         players.push(Player {
             id: 1,
@@ -496,6 +1120,7 @@ mod tests {
             categories: hashset!{1},
             price: 5200,
             projected_points: 24.8,
+            ..Default::default()
         });
         players.push(Player {
             id: 2,
@@ -505,6 +1130,7 @@ mod tests {
             categories: hashset!{2},
             price: 7000,
             projected_points: 21.2,
+            ..Default::default()
         });
         players.push(Player {
             id: 3,
@@ -514,6 +1140,7 @@ mod tests {
             categories: hashset!{2},
             price: 6700,
             projected_points: 19.8,
+            ..Default::default()
         });
         players.push(Player {
             id: 4,
@@ -523,6 +1150,7 @@ mod tests {
             categories: hashset!{2},
             price: 7300,
             projected_points: 26.0,
+            ..Default::default()
         });
         players.push(Player {
             id: 5,
@@ -532,6 +1160,7 @@ mod tests {
             categories: hashset!{3},
             price: 3700,
             projected_points: 10.4,
+            ..Default::default()
         });
         players.push(Player {
             id: 6,
@@ -541,6 +1170,7 @@ mod tests {
             categories: hashset!{3},
             price: 6800,
             projected_points: 19.9,
+            ..Default::default()
         });
         players.push(Player {
             id: 7,
@@ -550,10 +1180,23 @@ mod tests {
             categories: hashset!{3},
             price: 6700,
             projected_points: 17.3,
+            ..Default::default()
         });
         players
     }
 
+    fn get_test_players_with_ownership() -> Vec<Player> {
+        let mut players = get_test_players();
+        for player in &mut players {
+            player.ownership = match player.id {
+                1 => 30.0,
+                4 => 40.0,
+                _ => 5.0,
+            };
+        }
+        players
+    }
+
     fn get_test_players_multi_category() -> Vec<Player> {
         let mut players = Vec::new();
         players.push(Player {
@@ -564,6 +1207,7 @@ mod tests {
             categories: hashset!{1},
             price: 4500,
             projected_points: 18.4,
+            ..Default::default()
         });
         players.push(Player {
             id: 1,
@@ -573,6 +1217,7 @@ mod tests {
             categories: hashset!{1},
             price: 5200,
             projected_points: 24.8,
+            ..Default::default()
         });
         players.push(Player {
             id: 2,
@@ -582,6 +1227,7 @@ mod tests {
             categories: hashset!{2, 4},
             price: 7000,
             projected_points: 21.2,
+            ..Default::default()
         });
         players.push(Player {
             id: 3,
@@ -591,6 +1237,7 @@ mod tests {
             categories: hashset!{2, 4},
             price: 6700,
             projected_points: 19.8,
+            ..Default::default()
         });
         players.push(Player {
             id: 4,
@@ -600,6 +1247,7 @@ mod tests {
             categories: hashset!{2, 4},
             price: 7300,
             projected_points: 26.0,
+            ..Default::default()
         });
         players.push(Player {
             id: 5,
@@ -609,6 +1257,7 @@ mod tests {
             categories: hashset!{3, 4},
             price: 3700,
             projected_points: 10.4,
+            ..Default::default()
         });
         players.push(Player {
             id: 6,
@@ -618,6 +1267,7 @@ mod tests {
             categories: hashset!{3, 4},
             price: 6800,
             projected_points: 19.9,
+            ..Default::default()
         });
         players.push(Player {
             id: 7,
@@ -627,6 +1277,7 @@ mod tests {
             categories: hashset!{3, 4},
             price: 6700,
             projected_points: 17.3,
+            ..Default::default()
         });
         players
     }
@@ -638,6 +1289,8 @@ mod tests {
             count: 1,
             salary_multiplier: 1.0,
             point_multiplier: 1.0,
+            any_position: false,
+            flex: false,
         };
 
         let rb_slot = RosterSlot {
@@ -646,6 +1299,8 @@ mod tests {
             count: 1,
             salary_multiplier: 1.0,
             point_multiplier: 1.0,
+            any_position: false,
+            flex: false,
         };
 
         let wr_slot = RosterSlot {
@@ -654,11 +1309,37 @@ mod tests {
             count: 1,
             salary_multiplier: 1.0,
             point_multiplier: 1.0,
+            any_position: false,
+            flex: false,
         };
 
         vec![qb_slot, rb_slot, wr_slot]
     }
 
+    fn get_test_roster_slots_showdown() -> Vec<RosterSlot> {
+        let cpt_slot = RosterSlot {
+            name: "Captain".to_string(),
+            key: "CPT".to_string(),
+            count: 1,
+            salary_multiplier: 1.5,
+            point_multiplier: 1.5,
+            any_position: false,
+            flex: false,
+        };
+
+        let flex_slot = RosterSlot {
+            name: "Flex".to_string(),
+            key: "FLEX".to_string(),
+            count: 2,
+            salary_multiplier: 1.0,
+            point_multiplier: 1.0,
+            any_position: false,
+            flex: true,
+        };
+
+        vec![cpt_slot, flex_slot]
+    }
+
     fn get_test_roster_slots_flex() -> Vec<RosterSlot> {
         let qb_slot = RosterSlot {
             name: "Quarterback".to_string(),
@@ -666,6 +1347,8 @@ mod tests {
             count: 1,
             salary_multiplier: 1.0,
             point_multiplier: 1.0,
+            any_position: false,
+            flex: false,
         };
 
         let rb_slot = RosterSlot {
@@ -674,6 +1357,8 @@ mod tests {
             count: 1,
             salary_multiplier: 1.0,
             point_multiplier: 1.0,
+            any_position: false,
+            flex: false,
         };
 
         let wr_slot = RosterSlot {
@@ -682,6 +1367,8 @@ mod tests {
             count: 1,
             salary_multiplier: 1.0,
             point_multiplier: 1.0,
+            any_position: false,
+            flex: false,
         };
 
         let flex_slot = RosterSlot {
@@ -690,6 +1377,8 @@ mod tests {
             count: 1,
             salary_multiplier: 1.0,
             point_multiplier: 1.0,
+            any_position: false,
+            flex: true,
         };
 
         vec![qb_slot, rb_slot, wr_slot, flex_slot]
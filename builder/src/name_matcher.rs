@@ -0,0 +1,237 @@
+use std::collections::{ HashMap };
+use std::io::{ Read };
+use serde::{ Deserialize, Serialize };
+use crate::common::{ Player };
+
+/// Minimum similarity for a fuzzy match to be accepted
+pub const MIN_MATCH_CONFIDENCE: f64 = 0.85;
+/// Fuzzy matches below this similarity are reported for review
+pub const LOW_MATCH_CONFIDENCE: f64 = 0.95;
+
+/// Similarity given to a name whose tokens are all contained in the other name and that ends with the same surname,
+/// i.e. "Giants" and "New York Giants". Only applied to players on the same team
+const TOKEN_SUBSET_CONFIDENCE: f64 = 0.9;
+const SUFFIXES: [&str; 8] = ["jr", "sr", "ii", "iii", "iv", "v", "dst", "defense"];
+
+/// Matches player names and teams from an external source onto the players of a slate.
+/// Names are compared exactly, then normalized, then fuzzily among players on the same team with a similar surname.
+/// User supplied aliases are applied before any comparison.
+#[derive(Debug, Clone, Default)]
+pub struct NameMatcher {
+    team_aliases: HashMap<String, String>,
+    name_aliases: HashMap<String, String>,
+}
+
+impl NameMatcher {
+    pub fn new() -> NameMatcher {
+        NameMatcher::default()
+    }
+
+    /// Loads an alias file with the columns Type,Alias,Name where Type is either `team` or `player`,
+    /// `Alias` is the spelling used by the external source and `Name` the spelling used by the slate
+    pub fn from_alias_file(file_path: &str) -> Result<NameMatcher, String> {
+        let reader = csv::Reader::from_path(file_path)
+            .map_err(|err| format!("failed to open alias file {}: {}", file_path, err))?;
+        NameMatcher::from_alias_rows(reader)
+    }
+
+    fn from_alias_rows<R: Read>(mut reader: csv::Reader<R>) -> Result<NameMatcher, String> {
+        let mut matcher = NameMatcher::new();
+        for result in reader.deserialize::<AliasDataRow>() {
+            let record = result.map_err(|err| format!("error parsing alias csv: {}", err))?;
+            match record.alias_type.trim().to_lowercase().as_str() {
+                "team" => {
+                    matcher.team_aliases.insert(record.alias.trim().to_uppercase(), record.name.trim().to_uppercase());
+                },
+                "player" => {
+                    matcher.name_aliases.insert(normalize_name(&record.alias), record.name.trim().to_string());
+                },
+                other => return Err(format!("unknown alias type '{}' for alias {}", other, record.alias)),
+            }
+        }
+        Ok(matcher)
+    }
+
+    /// Resolves a team abbreviation to the one used by the slate
+    pub fn team(&self, team: &str) -> String {
+        let team = team.trim().to_uppercase();
+        match self.team_aliases.get(&team) {
+            Some(alias) => alias.clone(),
+            None => team,
+        }
+    }
+
+    /// Resolves a player name to the one used by the slate
+    pub fn name(&self, name: &str) -> String {
+        match self.name_aliases.get(&normalize_name(name)) {
+            Some(alias) => alias.clone(),
+            None => name.trim().to_string(),
+        }
+    }
+
+    /// Finds the player in `players` that best matches `name` and `team`, along with the confidence of the match.
+    /// An empty `team` matches players on any team
+    pub fn find_match<'a>(&self, name: &str, team: &str, players: &'a [Player]) -> Option<(&'a Player, f64)> {
+        let name = self.name(name);
+        let team = self.team(team);
+        let candidates: Vec<&Player> = players.iter()
+            .filter(|p| team.is_empty() || self.team(&p.team) == team)
+            .collect();
+
+        if let Some(player) = candidates.iter().find(|p| p.name == name) {
+            return Some((player, 1.0));
+        }
+
+        let normalized = normalize_name(&name);
+        if let Some(player) = candidates.iter().find(|p| normalize_name(&p.name) == normalized) {
+            return Some((player, 1.0));
+        }
+
+        let mut best: Option<(&Player, f64)> = None;
+        for player in candidates {
+            let candidate = normalize_name(&player.name);
+            if similarity(surname(&normalized), surname(&candidate)) < MIN_MATCH_CONFIDENCE {
+                continue;
+            }
+            let mut confidence = similarity(&normalized, &candidate);
+            if !team.is_empty() && is_token_subset(&normalized, &candidate) {
+                confidence = confidence.max(TOKEN_SUBSET_CONFIDENCE);
+            }
+            if best.is_none_or(|(_, c)| confidence > c) {
+                best = Some((player, confidence));
+            }
+        }
+        best.filter(|(_, confidence)| *confidence >= MIN_MATCH_CONFIDENCE)
+    }
+}
+
+/// Lowercases a name, folds accented characters, strips punctuation and drops
+/// suffixes like "Jr." or "D/ST"
+pub fn normalize_name(name: &str) -> String {
+    let lowered = name.to_lowercase().replace("d/st", " dst ");
+    let mut folded = String::with_capacity(lowered.len());
+    for c in lowered.chars() {
+        let c = fold_accent(c);
+        if c.is_alphanumeric() {
+            folded.push(c);
+        } else if c.is_whitespace() || c == '-' {
+            folded.push(' ');
+        }
+    }
+    folded.split_whitespace()
+        .filter(|token| !SUFFIXES.contains(token))
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Similarity of two normalized names between 0 and 1
+pub fn similarity(a: &str, b: &str) -> f64 {
+    strsim::jaro_winkler(a, b)
+}
+
+/// The last token of a normalized name
+fn surname(name: &str) -> &str {
+    name.split_whitespace().last().unwrap_or("")
+}
+
+/// Whether every token of the shorter normalized name is in the longer one and both end with the same surname
+fn is_token_subset(a: &str, b: &str) -> bool {
+    let (shorter, longer) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    let longer_tokens: Vec<&str> = longer.split_whitespace().collect();
+    !shorter.is_empty() && surname(shorter) == surname(longer)
+        && shorter.split_whitespace().all(|token| longer_tokens.contains(&token))
+}
+
+fn fold_accent(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'ç' | 'ć' | 'č' => 'c',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'ñ' | 'ń' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+        'š' => 's',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ž' => 'z',
+        _ => c,
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct AliasDataRow {
+    #[serde(rename = "Type")]
+    alias_type: String,
+
+    #[serde(rename = "Alias")]
+    alias: String,
+
+    #[serde(rename = "Name")]
+    name: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        assert_eq!(normalize_name("Odell Beckham Jr."), "odell beckham");
+        assert_eq!(normalize_name("José Ramírez"), "jose ramirez");
+        assert_eq!(normalize_name("Giants D/ST"), "giants");
+        assert_eq!(normalize_name("T.J. Hockenson"), "tj hockenson");
+        assert_eq!(normalize_name("Amon-Ra St. Brown"), "amon ra st brown");
+    }
+
+    #[test]
+    fn find_match() {
+        let players = get_test_players();
+        let matcher = NameMatcher::new();
+        let (player, confidence) = matcher.find_match("Odell Beckham", "CLE", &players).unwrap();
+        assert_eq!(player.id, 1);
+        assert_eq!(confidence, 1.0);
+        let (player, confidence) = matcher.find_match("New York Giants", "NYG", &players).unwrap();
+        assert_eq!(player.id, 3);
+        assert!(confidence < LOW_MATCH_CONFIDENCE);
+        let (player, _) = matcher.find_match("Jose Ramirez", "", &players).unwrap();
+        assert_eq!(player.id, 2);
+        assert!(matcher.find_match("Odell Beckham", "NYG", &players).is_none());
+        assert!(matcher.find_match("Nobody Special", "CLE", &players).is_none());
+    }
+
+    #[test]
+    fn token_subset() {
+        let players = get_test_players();
+        let matcher = NameMatcher::new();
+        // a shared first name isn't enough when the surname differs
+        assert!(matcher.find_match("Allen", "CHI", &players).is_none());
+        assert!(matcher.find_match("Allen Robinson Lee", "CHI", &players).is_none());
+        // a token subset is only trusted among players on the same team
+        assert!(matcher.find_match("New York Giants", "", &players).is_none());
+        assert!(is_token_subset("giants", "new york giants"));
+        assert!(!is_token_subset("allen", "allen robinson"));
+    }
+
+    #[test]
+    fn aliases() {
+        let players = get_test_players();
+        let data = "Type,Alias,Name\nteam,CLV,CLE\nplayer,OBJ,Odell Beckham Jr.\n";
+        let matcher = NameMatcher::from_alias_rows(csv::Reader::from_reader(data.as_bytes())).unwrap();
+        assert_eq!(matcher.team("clv"), "CLE");
+        let (player, confidence) = matcher.find_match("OBJ", "CLV", &players).unwrap();
+        assert_eq!(player.id, 1);
+        assert_eq!(confidence, 1.0);
+
+        let invalid = "Type,Alias,Name\ncoach,A,B\n";
+        assert!(NameMatcher::from_alias_rows(csv::Reader::from_reader(invalid.as_bytes())).is_err());
+    }
+
+    fn get_test_players() -> Vec<Player> {
+        vec![
+            Player { id: 1, name: String::from("Odell Beckham Jr."), team: String::from("CLE"), ..Default::default() },
+            Player { id: 2, name: String::from("José Ramírez"), team: String::from("CLE"), ..Default::default() },
+            Player { id: 3, name: String::from("Giants "), team: String::from("NYG"), ..Default::default() },
+            Player { id: 4, name: String::from("Allen Robinson II"), team: String::from("CHI"), ..Default::default() },
+        ]
+    }
+}
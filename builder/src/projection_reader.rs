@@ -0,0 +1,307 @@
+use std::collections::{ HashMap, HashSet };
+use std::io::{ Read };
+use serde::{ Deserialize, Serialize };
+use crate::common::{ Player };
+use crate::name_matcher::{ NameMatcher, LOW_MATCH_CONFIDENCE };
+
+/// A single row of an external projections file
+#[derive(Debug, Clone, Default)]
+pub struct Projection {
+    /// provider (DK/FD) id of the player, if the projection source includes it. Matched against both
+    /// `Player::id` and `Player::provider_id`
+    pub id: Option<String>,
+    pub name: String,
+    pub team: String,
+    pub points: f64,
+    /// projected ownership as a percentage, if the projection source includes it
+    pub ownership: Option<f64>,
+}
+
+/// The rows on either side of a merge that couldn't be matched
+#[derive(Debug, Clone, Default)]
+pub struct MergeReport {
+    /// projections without a player in the slate
+    pub unmatched_projections: Vec<Projection>,
+    /// players in the slate without a projection
+    pub unmatched_players: Vec<Player>,
+    /// fuzzy matches that should be double checked, along with their confidence
+    pub low_confidence: Vec<(Projection, Player, f64)>,
+}
+
+pub fn read_projections(file_path: &str) -> Result<Vec<Projection>, String> {
+    let reader = csv::Reader::from_path(file_path)
+        .map_err(|err| format!("failed to open projections file {}: {}", file_path, err))?;
+    read_projection_rows(reader)
+}
+
+fn read_projection_rows<R: Read>(mut reader: csv::Reader<R>) -> Result<Vec<Projection>, String> {
+    let mut projections: Vec<Projection> = Vec::new();
+    for result in reader.deserialize::<ProjectionDataRow>() {
+        match result {
+            Ok(record) => {
+                projections.push(Projection {
+                    id: record.id.filter(|id| !id.is_empty()),
+                    name: record.name,
+                    team: record.team,
+                    points: record.projection,
+                    ownership: record.ownership,
+                });
+            },
+            Err(err) => return Err(format!("error parsing projections csv: {}", err)),
+        }
+    }
+    Ok(projections)
+}
+
+/// A projections file and how much it counts towards the blended projection
+#[derive(Debug, Clone, Default)]
+pub struct ProjectionSource {
+    pub projections: Vec<Projection>,
+    pub weight: f64,
+}
+
+/// Sets each player's points, spread and ownership to the weighted blend of the best matching row from each source
+pub fn merge_projections(players: &mut [Player], sources: &[ProjectionSource], matcher: &NameMatcher) -> MergeReport {
+    let mut report = MergeReport::default();
+    let mut projected: HashMap<(String, String, String), Vec<(f64, f64)>> = HashMap::new();
+    let mut owned: HashMap<(String, String, String), Vec<(f64, f64)>> = HashMap::new();
+    for source in sources {
+        // a source only counts once per player, duplicate rows keep the most confident match
+        let mut best: HashMap<(String, String, String), (&Projection, f64)> = HashMap::new();
+        for projection in &source.projections {
+            let matched = projection.id.as_ref()
+                .and_then(|id| players.iter().find(|p| &p.provider_id == id || &p.id.to_string() == id))
+                .map(|p| (p, 1.0))
+                .or_else(|| matcher.find_match(&projection.name, &projection.team, players));
+            match matched {
+                Some((player, confidence)) => {
+                    if confidence < LOW_MATCH_CONFIDENCE {
+                        report.low_confidence.push((projection.clone(), player.clone(), confidence));
+                    }
+                    let key = player.identity_key();
+                    if best.get(&key).is_none_or(|(_, best_confidence)| confidence > *best_confidence) {
+                        best.insert(key, (projection, confidence));
+                    }
+                },
+                None => report.unmatched_projections.push(projection.clone()),
+            }
+        }
+        for (key, (projection, _)) in best {
+            if let Some(ownership) = projection.ownership {
+                owned.entry(key.clone()).or_default().push((ownership, source.weight));
+            }
+            projected.entry(key).or_default().push((projection.points, source.weight));
+        }
+    }
+
+    let mut unmatched: HashSet<(String, String, String)> = HashSet::new();
+    for player in players.iter_mut() {
+        let key = player.identity_key();
+        match projected.get(&key) {
+            Some(points) => {
+                let (mean, std_dev) = weighted_mean_std_dev(points);
+                player.projected_points = mean;
+                player.std_dev = std_dev;
+                if let Some(ownership) = owned.get(&key) {
+                    player.ownership = weighted_mean_std_dev(ownership).0;
+                }
+            },
+            None => {
+                if unmatched.insert(key) {
+                    report.unmatched_players.push(player.clone());
+                }
+            }
+        }
+    }
+    report
+}
+
+/// Weighted mean and (population) standard deviation of `(value, weight)` pairs
+fn weighted_mean_std_dev(values: &[(f64, f64)]) -> (f64, f64) {
+    let total_weight: f64 = values.iter().map(|(_, w)| w).sum();
+    if total_weight <= 0.0 {
+        return (0.0, 0.0);
+    }
+    let mean = values.iter().map(|(v, w)| v * w).sum::<f64>() / total_weight;
+    let variance = values.iter().map(|(v, w)| w * (v - mean).powi(2)).sum::<f64>() / total_weight;
+    (mean, variance.sqrt())
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ProjectionDataRow {
+    #[serde(rename = "ID", default)]
+    id: Option<String>,
+
+    #[serde(rename = "Name")]
+    name: String,
+
+    #[serde(rename = "Team")]
+    team: String,
+
+    #[serde(rename = "Projection")]
+    projection: f64,
+
+    #[serde(rename = "Ownership", default)]
+    ownership: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_rows() {
+        let data = "ID,Name,Team,Projection\n13041692,Saquon Barkley,NYG,24.5\n,Ezekiel Elliott,DAL,22.1\n";
+        let projections = read_projection_rows(csv::Reader::from_reader(data.as_bytes())).unwrap();
+        assert_eq!(projections.len(), 2);
+        assert_eq!(projections[0].id, Some("13041692".to_string()));
+        assert_eq!(projections[1].id, None);
+        assert_eq!(projections[1].points, 22.1);
+        assert_eq!(projections[0].ownership, None);
+    }
+
+    #[test]
+    fn read_rows_with_ownership() {
+        let data = "Name,Team,Projection,Ownership\nSaquon Barkley,NYG,24.5,32.5\nEzekiel Elliott,DAL,22.1,\n";
+        let projections = read_projection_rows(csv::Reader::from_reader(data.as_bytes())).unwrap();
+        assert_eq!(projections[0].ownership, Some(32.5));
+        assert_eq!(projections[1].ownership, None);
+    }
+
+    #[test]
+    fn read_rows_without_id() {
+        let data = "Name,Team,Projection\nSaquon Barkley,NYG,24.5\n";
+        let projections = read_projection_rows(csv::Reader::from_reader(data.as_bytes())).unwrap();
+        assert_eq!(projections[0].id, None);
+        assert_eq!(projections[0].name, "Saquon Barkley");
+    }
+
+    #[test]
+    fn merge() {
+        let mut players = vec![
+            test_player(1, "Saquon Barkley", "NYG"),
+            test_player(2, "Saquon Barkley", "NYG"),
+            test_player(3, "Ezekiel Elliott", "DAL"),
+            test_player(4, "Dak Prescott", "DAL"),
+        ];
+        let projections = vec![
+            Projection { id: Some("1".to_string()), name: String::from("S. Barkley"), team: String::from("NYG"), points: 24.5, ownership: None },
+            Projection { id: None, name: String::from("Ezekiel Elliott"), team: String::from("DAL"), points: 22.1, ownership: None },
+            Projection { id: None, name: String::from("Nobody"), team: String::from("DAL"), points: 1.0, ownership: None },
+        ];
+        let report = merge_projections(&mut players, &[single_source(projections)], &NameMatcher::new());
+        assert_eq!(players[0].projected_points, 24.5);
+        assert_eq!(players[1].projected_points, 24.5);
+        assert_eq!(players[2].projected_points, 22.1);
+        assert_eq!(players[3].projected_points, 10.0);
+        assert_eq!(report.unmatched_projections.len(), 1);
+        assert_eq!(report.unmatched_projections[0].name, "Nobody");
+        assert_eq!(report.unmatched_players.len(), 1);
+        assert_eq!(report.unmatched_players[0].name, "Dak Prescott");
+        assert!(report.low_confidence.is_empty());
+    }
+
+    #[test]
+    fn merge_fuzzy() {
+        let mut players = vec![
+            test_player(1, "Odell Beckham Jr.", "CLE"),
+            test_player(2, "Giants ", "NYG"),
+        ];
+        let projections = vec![
+            Projection { id: None, name: String::from("Odell Beckham"), team: String::from("CLE"), points: 15.2, ownership: None },
+            Projection { id: None, name: String::from("New York Giants D/ST"), team: String::from("NYG"), points: 6.0, ownership: None },
+        ];
+        let report = merge_projections(&mut players, &[single_source(projections)], &NameMatcher::new());
+        assert_eq!(players[0].projected_points, 15.2);
+        assert_eq!(players[1].projected_points, 6.0);
+        assert!(report.unmatched_projections.is_empty());
+        assert_eq!(report.low_confidence.len(), 1);
+        assert_eq!(report.low_confidence[0].1.id, 2);
+    }
+
+    #[test]
+    fn merge_provider_id() {
+        let mut players = vec![test_player(1, "Odell Beckham Jr.", "CLE")];
+        players[0].provider_id = String::from("37319-104449");
+        let projections = vec![
+            Projection { id: Some("37319-104449".to_string()), name: String::from("OBJ"), team: String::from("CLE"), points: 15.2, ownership: None },
+        ];
+        let report = merge_projections(&mut players, &[single_source(projections)], &NameMatcher::new());
+        assert_eq!(players[0].projected_points, 15.2);
+        assert!(report.unmatched_projections.is_empty());
+    }
+
+    #[test]
+    fn merge_weighted() {
+        let mut players = vec![
+            test_player(1, "Saquon Barkley", "NYG"),
+            test_player(2, "Ezekiel Elliott", "DAL"),
+        ];
+        let sources = vec![
+            ProjectionSource {
+                projections: vec![
+                    Projection { id: None, name: String::from("Saquon Barkley"), team: String::from("NYG"), points: 20.0, ownership: Some(30.0) },
+                    Projection { id: None, name: String::from("Ezekiel Elliott"), team: String::from("DAL"), points: 18.0, ownership: None },
+                ],
+                weight: 3.0,
+            },
+            ProjectionSource {
+                projections: vec![
+                    Projection { id: None, name: String::from("Saquon Barkley"), team: String::from("NYG"), points: 24.0, ownership: Some(10.0) },
+                ],
+                weight: 1.0,
+            },
+        ];
+        let report = merge_projections(&mut players, &sources, &NameMatcher::new());
+        assert!(report.unmatched_players.is_empty());
+        assert!((players[0].projected_points - 21.0).abs() < 1e-9);
+        assert!((players[0].std_dev - 3.0_f64.sqrt()).abs() < 1e-9);
+        assert_eq!(players[1].projected_points, 18.0);
+        assert_eq!(players[1].std_dev, 0.0);
+        assert!((players[0].ownership - 25.0).abs() < 1e-9);
+        assert_eq!(players[1].ownership, 0.0);
+    }
+
+    #[test]
+    fn merge_duplicate_rows() {
+        let mut players = vec![
+            test_player(1, "Saquon Barkley", "NYG"),
+            test_player(2, "Saquon Barkley", "NYG"),
+        ];
+        let sources = vec![
+            ProjectionSource {
+                projections: vec![
+                    Projection { id: None, name: String::from("S. Barkley"), team: String::from("NYG"), points: 30.0, ownership: Some(40.0) },
+                    Projection { id: Some("2".to_string()), name: String::from("Saquon Barkley"), team: String::from("NYG"), points: 20.0, ownership: Some(20.0) },
+                ],
+                weight: 1.0,
+            },
+            ProjectionSource {
+                projections: vec![
+                    Projection { id: None, name: String::from("Saquon Barkley"), team: String::from("NYG"), points: 24.0, ownership: Some(10.0) },
+                ],
+                weight: 1.0,
+            },
+        ];
+        merge_projections(&mut players, &sources, &NameMatcher::new());
+        // only the id row of the first source is blended with the second source
+        assert!((players[0].projected_points - 22.0).abs() < 1e-9);
+        assert!((players[0].std_dev - 2.0).abs() < 1e-9);
+        assert!((players[0].ownership - 15.0).abs() < 1e-9);
+        assert_eq!(players[1].projected_points, players[0].projected_points);
+    }
+
+    fn single_source(projections: Vec<Projection>) -> ProjectionSource {
+        ProjectionSource { projections, weight: 1.0 }
+    }
+
+    fn test_player(id: u64, name: &str, team: &str) -> Player {
+        Player {
+            id,
+            name: String::from(name),
+            team: String::from(team),
+            projected_points: 10.0,
+            ..Default::default()
+        }
+    }
+}
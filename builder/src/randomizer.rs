@@ -0,0 +1,79 @@
+use std::collections::{ HashMap };
+use rand::{ Rng };
+use rand_distr::{ StandardNormal };
+use crate::common::{ Player };
+use crate::player_pool::{ PlayerPool };
+
+/// How much projections are randomly perturbed before each solve, used to diversify a set of lineups
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Randomness {
+    /// shift each projection by a uniformly random amount of up to this percentage in either direction
+    Percent(f64),
+    /// shift each projection by a normally distributed amount scaled by the player's `std_dev`, which requires
+    /// blending more than one projections file
+    StdDev,
+}
+
+/// Returns a copy of `player_pool` with randomly perturbed projections. Entries with the same `Player::identity_key`
+/// are shifted by the same amount
+pub fn perturb_player_pool<R: Rng>(player_pool: &PlayerPool, randomness: Randomness, rng: &mut R) -> PlayerPool {
+    let mut players = player_pool.get_all();
+    players.sort_by_key(|p| p.id);
+    let mut draws: HashMap<(String, String, String), f64> = HashMap::new();
+    for player in &mut players {
+        let draw = *draws.entry(player.identity_key()).or_insert_with(|| match randomness {
+            Randomness::Percent(_) => rng.gen_range(-1.0..=1.0),
+            Randomness::StdDev => rng.sample(StandardNormal),
+        });
+        player.projected_points = perturb(player, randomness, draw);
+    }
+    PlayerPool::new(players, false)
+}
+
+fn perturb(player: &Player, randomness: Randomness, draw: f64) -> f64 {
+    match randomness {
+        Randomness::Percent(percent) => player.projected_points * (1.0 + draw * percent / 100.0),
+        Randomness::StdDev => player.projected_points + draw * player.std_dev,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{ SeedableRng };
+    use rand::rngs::{ StdRng };
+
+    #[test]
+    fn perturb_percent() {
+        let pool = get_test_pool();
+        let mut rng = StdRng::seed_from_u64(11);
+        let perturbed = perturb_player_pool(&pool, Randomness::Percent(20.0), &mut rng);
+        let captain = perturbed.get_player(&1).unwrap().projected_points;
+        let flex = perturbed.get_player(&2).unwrap().projected_points;
+        assert!((16.0..=24.0).contains(&captain));
+        assert_eq!(captain, flex);
+
+        let mut rng = StdRng::seed_from_u64(11);
+        let repeated = perturb_player_pool(&pool, Randomness::Percent(20.0), &mut rng);
+        assert_eq!(repeated.get_player(&3).unwrap().projected_points, perturbed.get_player(&3).unwrap().projected_points);
+    }
+
+    #[test]
+    fn perturb_std_dev() {
+        let pool = get_test_pool();
+        let mut rng = StdRng::seed_from_u64(5);
+        let perturbed = perturb_player_pool(&pool, Randomness::StdDev, &mut rng);
+        assert_ne!(perturbed.get_player(&1).unwrap().projected_points, 20.0);
+        // no spread means no perturbation
+        assert_eq!(perturbed.get_player(&3).unwrap().projected_points, 15.0);
+    }
+
+    fn get_test_pool() -> PlayerPool {
+        let players = vec![
+            Player { id: 1, name: String::from("Saquon Barkley"), team: String::from("NYG"), projected_points: 20.0, std_dev: 5.0, ..Default::default() },
+            Player { id: 2, name: String::from("Saquon Barkley"), team: String::from("NYG"), projected_points: 20.0, std_dev: 5.0, ..Default::default() },
+            Player { id: 3, name: String::from("Daniel Jones"), team: String::from("NYG"), projected_points: 15.0, std_dev: 0.0, ..Default::default() },
+        ];
+        PlayerPool::new(players, false)
+    }
+}
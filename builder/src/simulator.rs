@@ -0,0 +1,277 @@
+use std::collections::{ HashMap };
+use rand::{ Rng, SeedableRng };
+use rand::rngs::{ StdRng };
+use rand_distr::{ StandardNormal };
+use crate::builder::{ Lineup };
+use crate::common::{ Player };
+use crate::correlation::{ CorrelationModel };
+
+/// Each attempt to factor a correlation matrix that isn't positive definite shrinks the off-diagonal entries by this
+const CORRELATION_SHRINK: f64 = 0.9;
+const MAX_SHRINK_ATTEMPTS: u32 = 50;
+
+/// Distribution of a lineup's score across all simulated slate outcomes
+#[derive(Debug, Clone)]
+pub struct LineupSimulation {
+    pub mean: f64,
+    pub p90: f64,
+    pub p99: f64,
+    /// probability the lineup scores the most points among the simulated set, ties are split evenly
+    pub win_probability: f64,
+}
+
+/// Simulates `iterations` slate outcomes and scores every lineup in each. Each player's score is drawn from a normal
+/// distribution around `Player::projected_points` with `Player::std_dev`, correlated with the other players in their
+/// game by `correlations`, and the same draw is used for every lineup the player is in. A `seed` makes the
+/// simulation reproducible.
+pub fn simulate_lineups(lineups: &[Lineup], iterations: u32, seed: Option<u64>, correlations: &CorrelationModel) -> Result<Vec<LineupSimulation>, String> {
+    if iterations == 0 {
+        return Err("simulation requires at least one iteration".to_string());
+    }
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let players = unique_players(lineups);
+    let cholesky = factor_correlations(&players, correlations);
+    let mut scores: Vec<Vec<f64>> = vec![Vec::with_capacity(iterations as usize); lineups.len()];
+    let mut wins: Vec<f64> = vec![0.0; lineups.len()];
+    for _ in 0..iterations {
+        let outcomes = sample_outcomes(&players, &cholesky, &mut rng);
+        let iteration_scores: Vec<f64> = lineups.iter().map(|lineup| score_lineup(lineup, &outcomes)).collect();
+        let best = iteration_scores.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let winners = iteration_scores.iter().filter(|score| **score == best).count() as f64;
+        for (i, score) in iteration_scores.into_iter().enumerate() {
+            if score == best {
+                wins[i] += 1.0 / winners;
+            }
+            scores[i].push(score);
+        }
+    }
+
+    Ok(scores.into_iter().zip(wins).map(|(mut lineup_scores, win_count)| {
+        lineup_scores.sort_by(|a,b| a.partial_cmp(b).unwrap());
+        LineupSimulation {
+            mean: lineup_scores.iter().sum::<f64>() / iterations as f64,
+            p90: percentile(&lineup_scores, 0.9),
+            p99: percentile(&lineup_scores, 0.99),
+            win_probability: win_count / iterations as f64,
+        }
+    }).collect())
+}
+
+fn unique_players(lineups: &[Lineup]) -> Vec<&Player> {
+    let mut players: Vec<&Player> = Vec::new();
+    for lineup in lineups {
+        for player in lineup.players() {
+            if !players.iter().any(|p| p.identity_key() == player.identity_key()) {
+                players.push(player);
+            }
+        }
+    }
+    players
+}
+
+/// Lower triangular Cholesky factor of the correlation matrix between `players`. Combinations of pairwise
+/// correlations aren't always a valid correlation matrix, so the off-diagonal entries are shrunk until it can
+/// be factored, falling back to uncorrelated outcomes
+fn factor_correlations(players: &[&Player], correlations: &CorrelationModel) -> Vec<Vec<f64>> {
+    let n = players.len();
+    let mut matrix = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..n {
+            matrix[i][j] = if i == j { 1.0 } else { correlations.correlation(players[i], players[j]) };
+        }
+    }
+    for _ in 0..MAX_SHRINK_ATTEMPTS {
+        if let Some(factor) = cholesky(&matrix) {
+            return factor;
+        }
+        for (i, row) in matrix.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                if i != j {
+                    *value *= CORRELATION_SHRINK;
+                }
+            }
+        }
+    }
+    (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect()
+}
+
+/// Cholesky decomposition of a symmetric matrix, `None` if the matrix isn't positive definite
+fn cholesky(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let mut factor = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| factor[i][k] * factor[j][k]).sum();
+            if i == j {
+                let diagonal = matrix[i][i] - sum;
+                if diagonal <= 0.0 {
+                    return None;
+                }
+                factor[i][j] = diagonal.sqrt();
+            } else {
+                factor[i][j] = (matrix[i][j] - sum) / factor[j][j];
+            }
+        }
+    }
+    Some(factor)
+}
+
+/// Draws the number of standard deviations each player lands from their projection in a single slate outcome
+fn sample_outcomes<R: Rng>(players: &[&Player], cholesky: &[Vec<f64>], rng: &mut R) -> HashMap<(String, String, String), f64> {
+    let independent: Vec<f64> = players.iter().map(|_| rng.sample::<f64, _>(StandardNormal)).collect();
+    players.iter().enumerate()
+        .map(|(i, player)| {
+            let correlated: f64 = (0..=i).map(|k| cholesky[i][k] * independent[k]).sum();
+            (player.identity_key(), correlated)
+        })
+        .collect()
+}
+
+/// Lineup players carry slot scaled points and deviations, so the draw is applied to each entry directly
+fn score_lineup(lineup: &Lineup, outcomes: &HashMap<(String, String, String), f64>) -> f64 {
+    lineup.players().iter()
+        .map(|player| {
+            let deviations = outcomes.get(&player.identity_key()).cloned().unwrap_or(0.0);
+            player.projected_points + deviations * player.std_dev
+        })
+        .sum()
+}
+
+/// Nearest-rank percentile of sorted `values`
+fn percentile(values: &[f64], p: f64) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let rank = (p * values.len() as f64).ceil() as usize;
+    values[rank.saturating_sub(1).min(values.len() - 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{ RosterSlot };
+    use crate::correlation::{ Relation };
+
+    #[test]
+    fn simulate_without_spread() {
+        let lineups = vec![
+            test_lineup(vec![test_player("Saquon Barkley", 26.0, 0.0), test_player("Todd Gurley", 21.2, 0.0)]),
+            test_lineup(vec![test_player("Saquon Barkley", 26.0, 0.0), test_player("Alvin Kamara", 19.8, 0.0)]),
+        ];
+        let results = simulate_lineups(&lineups, 100, Some(7), &CorrelationModel::new()).unwrap();
+        assert!((results[0].mean - 47.2).abs() < 1e-9);
+        assert!((results[0].p99 - 47.2).abs() < 1e-9);
+        assert_eq!(results[0].win_probability, 1.0);
+        assert_eq!(results[1].win_probability, 0.0);
+    }
+
+    #[test]
+    fn simulate_with_spread() {
+        let lineups = vec![
+            test_lineup(vec![test_player("Saquon Barkley", 20.0, 8.0)]),
+            test_lineup(vec![test_player("Todd Gurley", 20.0, 2.0)]),
+        ];
+        let results = simulate_lineups(&lineups, 5000, Some(42), &CorrelationModel::new()).unwrap();
+        assert!((results[0].mean - 20.0).abs() < 0.5);
+        assert!(results[0].p90 > results[1].p90);
+        assert!(results[0].p99 > results[0].p90);
+        let total: f64 = results.iter().map(|r| r.win_probability).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        let repeated = simulate_lineups(&lineups, 5000, Some(42), &CorrelationModel::new()).unwrap();
+        assert_eq!(results[0].p90, repeated[0].p90);
+        assert!(simulate_lineups(&lineups, 0, None, &CorrelationModel::new()).is_err());
+    }
+
+    #[test]
+    fn simulate_correlated() {
+        let mut qb = test_player("Daniel Jones", 20.0, 6.0);
+        qb.position = String::from("QB");
+        qb.opponent = String::from("DAL");
+        let mut wr = test_player("Darius Slayton", 12.0, 6.0);
+        wr.position = String::from("WR");
+        wr.opponent = String::from("DAL");
+        let mut dst = test_player("Cowboys ", 8.0, 4.0);
+        dst.position = String::from("DST");
+        dst.team = String::from("DAL");
+        dst.opponent = String::from("NYG");
+        let lineups = vec![test_lineup(vec![qb, wr, dst])];
+        let players = unique_players(&lineups);
+        let model = CorrelationModel::for_sport("nfl");
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let factor = factor_correlations(&players, &model);
+        let draws: Vec<HashMap<(String, String, String), f64>> = (0..20000).map(|_| sample_outcomes(&players, &factor, &mut rng)).collect();
+        let qb_wr = sample_correlation(&draws, players[0], players[1]);
+        let qb_dst = sample_correlation(&draws, players[0], players[2]);
+        assert!((qb_wr - 0.35).abs() < 0.05, "qb/wr correlation {}", qb_wr);
+        assert!((qb_dst + 0.35).abs() < 0.05, "qb/dst correlation {}", qb_dst);
+    }
+
+    #[test]
+    fn factor_invalid_correlations() {
+        let matrix = vec![
+            vec![1.0, 0.9, -0.9],
+            vec![0.9, 1.0, 0.9],
+            vec![-0.9, 0.9, 1.0],
+        ];
+        assert!(cholesky(&matrix).is_none());
+        let mut model = CorrelationModel::new();
+        model.set("A", "B", Relation::Teammate, 0.9);
+        model.set("A", "C", Relation::Teammate, -0.9);
+        model.set("B", "C", Relation::Teammate, 0.9);
+        let players: Vec<Player> = ["A", "B", "C"].iter().map(|pos| {
+            let mut player = test_player(pos, 10.0, 1.0);
+            player.position = pos.to_string();
+            player
+        }).collect();
+        let factor = factor_correlations(&players.iter().collect::<Vec<&Player>>(), &model);
+        assert!(factor[2][2] > 0.0);
+    }
+
+    #[test]
+    fn nearest_rank_percentile() {
+        let values: Vec<f64> = (1..=100).map(|v| v as f64).collect();
+        assert_eq!(percentile(&values, 0.9), 90.0);
+        assert_eq!(percentile(&values, 0.99), 99.0);
+        assert_eq!(percentile(&[5.0], 0.99), 5.0);
+    }
+
+    fn sample_correlation(draws: &[HashMap<(String, String, String), f64>], a: &Player, b: &Player) -> f64 {
+        let n = draws.len() as f64;
+        let xs: Vec<f64> = draws.iter().map(|d| d[&a.identity_key()]).collect();
+        let ys: Vec<f64> = draws.iter().map(|d| d[&b.identity_key()]).collect();
+        let mean_x = xs.iter().sum::<f64>() / n;
+        let mean_y = ys.iter().sum::<f64>() / n;
+        let cov: f64 = xs.iter().zip(&ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>() / n;
+        let var_x: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum::<f64>() / n;
+        let var_y: f64 = ys.iter().map(|y| (y - mean_y).powi(2)).sum::<f64>() / n;
+        cov / (var_x * var_y).sqrt()
+    }
+
+    fn test_lineup(players: Vec<Player>) -> Lineup {
+        let slot = RosterSlot {
+            name: String::from("Flex"),
+            key: String::from("FLEX"),
+            count: players.len() as u32,
+            salary_multiplier: 1.0,
+            point_multiplier: 1.0,
+            any_position: false,
+            flex: false,
+        };
+        Lineup::new(players.into_iter().map(|player| (slot.clone(), player)).collect())
+    }
+
+    fn test_player(name: &str, projected_points: f64, std_dev: f64) -> Player {
+        Player {
+            name: String::from(name),
+            team: String::from("NYG"),
+            projected_points,
+            std_dev,
+            ..Default::default()
+        }
+    }
+}
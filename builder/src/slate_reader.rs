@@ -3,9 +3,12 @@ use std::collections::{ HashMap, HashSet };
 use std::error::Error;
 use std::fs::{ File };
 use std::rc::{ Rc };
+use chrono::{ DateTime, FixedOffset, NaiveDateTime, TimeZone };
+use chrono_tz::{ Tz };
 use csv;
 use serde::{ Deserialize, Serialize };
-use crate::common::{ BuilderState, Player };
+use crate::common;
+use crate::common::{ BuilderState, Player, RosterSlot };
 use crate::category_mapper;
 use crate::player_pool::PlayerPool;
 
@@ -24,7 +27,20 @@ pub fn read_slate(file_path: &str, provider: &str, builder_state: &mut BuilderSt
     Ok(())
 }
 
+/// Adds the categories of any roster slot that every player is eligible for (i.e. FanDuel single game MVP)
+pub(crate) fn append_any_position_categories(categories: &mut HashSet<u32>, slots: &HashMap<u32, RosterSlot>) {
+    for (category, slot) in slots {
+        if slot.any_position {
+            categories.insert(*category);
+        }
+    }
+}
+
 fn read_dk_row(reader: &mut csv::Reader<File>, builder_state: &mut BuilderState, category_map: &HashMap<String, u32>) -> Result<(), &'static str> {
+    let slots = match &builder_state.roster_slots {
+        Some(ref rs) => common::map_roster_slots(rs, category_map),
+        None => HashMap::new(),
+    };
     let mut player_data_list: Vec<Player> = Vec::new();
     for result in reader.deserialize::<DKDataRow>() {
         match result {
@@ -42,31 +58,54 @@ fn read_dk_row(reader: &mut csv::Reader<File>, builder_state: &mut BuilderState,
                     categories.insert(*category.unwrap());
                 }
 
-                let mut player = Player {
+                append_any_position_categories(&mut categories, &slots);
+
+                // DK lists players once per slot for showdown slates, with the slot salary multiplier already applied
+                // to the salary (i.e. CPT). The optimizer applies the multiplier so store the base salary instead
+                let mut price = record.salary;
+                if categories.len() == 1 {
+                    if let Some(slot) = categories.iter().next().and_then(|c| slots.get(c)) {
+                        if slot.salary_multiplier > 0.0 && slot.salary_multiplier != 1.0 {
+                            price = (record.salary as f64 / slot.salary_multiplier).round() as u32;
+                        }
+                    }
+                }
+
+                let game_info = parse_game_info(&record.game_info);
+                let player = Player {
                     id: record.id,
+                    provider_id: record.id.to_string(),
                     name: record.name,
+                    opponent: game_info.opponent(&record.teamabbrev),
                     team: record.teamabbrev,
+                    home_team: game_info.home_team,
+                    away_team: game_info.away_team,
+                    game: game_info.game,
+                    start_time: game_info.start_time,
                     position: record.position,
-                    price: record.salary,
+                    price,
                     projected_points: record.avg_points_per_game,
+                    std_dev: 0.0,
+                    ownership: 0.0,
+                    injury_indicator: String::new(),
+                    injury_details: String::new(),
                     categories: categories,
                 };
-                // TODO: fix this, tie the salary multiplier to the roster slot?
-                if &record.roster_position == "CPT" {
-                    player.projected_points *= 1.5;
-                    player.price = ((player.price as f64) * 1.5) as u32; // this is bad lmao
-                }
                 player_data_list.push(player);
             },
             Err(_err) => return Err("error parsing csv")
         }
     }
-    let player_pool = PlayerPool::new(player_data_list, true);
+    let player_pool = PlayerPool::new(player_data_list, false);
     builder_state.player_pool = Some(player_pool);
     Ok(())
 }
 
 fn read_fanduel_row(reader: &mut csv::Reader<File>, builder_state: &mut BuilderState, category_map: &HashMap<String, u32>) -> Result<(), &'static str> {
+    let slots = match &builder_state.roster_slots {
+        Some(ref rs) => common::map_roster_slots(rs, category_map),
+        None => HashMap::new(),
+    };
     let mut player_data_list: Vec<Player> = Vec::new();
     let mut id: u64 = 1;
     for result in reader.deserialize::<FanDuelDataRow>() {
@@ -85,21 +124,28 @@ fn read_fanduel_row(reader: &mut csv::Reader<File>, builder_state: &mut BuilderS
                     };
                     categories.insert(*category.unwrap());
                 }
-                
-                let mut player = Player {
+                append_any_position_categories(&mut categories, &slots);
+
+                let game_info = parse_game_info(&record.game);
+                let player = Player {
                     id: id,
+                    provider_id: record.id,
                     name: record.nickname,
                     team: record.team,
+                    opponent: record.opponent,
+                    home_team: game_info.home_team,
+                    away_team: game_info.away_team,
+                    game: game_info.game,
+                    start_time: None,
                     position: record.position,
                     price: record.salary,
                     projected_points: record.fantasy_points_per_game,
+                    std_dev: 0.0,
+                    ownership: 0.0,
+                    injury_indicator: record.injury_indicator,
+                    injury_details: record.injury_details,
                     categories: categories,
                 };
-
-                // TODO: fix this, tie the salary multiplier to the roster slot?
-                // if &record.position == "CPT" {
-                //     player.projected_points *= 1.5;
-                // }
                 id += 1;
                 player_data_list.push(player);
             },
@@ -109,11 +155,68 @@ fn read_fanduel_row(reader: &mut csv::Reader<File>, builder_state: &mut BuilderS
             },
         }
     }
-    let player_pool = PlayerPool::new(player_data_list, true);
+    let player_pool = PlayerPool::new(player_data_list, false);
     builder_state.player_pool = Some(player_pool);
     Ok(())
 }
 
+/// The parts of DK's `Game Info` column, i.e. `NYG@DAL 09/08/2019 04:25PM ET`, or FanDuel's `Game` column
+/// which only has the matchup
+#[derive(Debug, Clone, Default)]
+struct GameInfo {
+    away_team: String,
+    home_team: String,
+    /// the matchup, i.e. `NYG@DAL`, this matches the format of FanDuel's `Game` column
+    game: String,
+    start_time: Option<DateTime<FixedOffset>>,
+}
+
+impl GameInfo {
+    /// The other team in the game, an empty string if `team` isn't playing in it
+    fn opponent(&self, team: &str) -> String {
+        if self.away_team == team {
+            self.home_team.clone()
+        } else if self.home_team == team {
+            self.away_team.clone()
+        } else {
+            String::new()
+        }
+    }
+}
+
+/// Parses game info, any part that can't be parsed is left empty (i.e. `Postponed`)
+fn parse_game_info(game_info: &str) -> GameInfo {
+    let parts: Vec<&str> = game_info.split_whitespace().collect();
+    let game = String::from(parts.first().cloned().unwrap_or(""));
+    let teams: Vec<&str> = game.split('@').collect();
+    let (away_team, home_team) = match teams.as_slice() {
+        [away, home] => (away.to_string(), home.to_string()),
+        _ => (String::new(), String::new()),
+    };
+    let start_time = match (parts.get(1), parts.get(2)) {
+        (Some(date), Some(time)) => parse_start_time(date, time, parts.get(3).cloned().unwrap_or("ET")),
+        _ => None,
+    };
+    GameInfo {
+        away_team,
+        home_team,
+        game,
+        start_time,
+    }
+}
+
+/// Parses a start time like `09/08/2019 04:25PM` in a US timezone abbreviation, i.e. `ET`
+fn parse_start_time(date: &str, time: &str, timezone: &str) -> Option<DateTime<FixedOffset>> {
+    let naive = NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%m/%d/%Y %I:%M%p").ok()?;
+    let tz: Tz = match timezone {
+        "CT" => chrono_tz::America::Chicago,
+        "MT" => chrono_tz::America::Denver,
+        "PT" => chrono_tz::America::Los_Angeles,
+        _ => chrono_tz::America::New_York,
+    };
+    tz.from_local_datetime(&naive).earliest().map(|start_time| start_time.fixed_offset())
+}
+
 fn append_flex_for_fanduel(category_keys: &mut Vec<&str>) {
     for key in &*category_keys {
         if *key == "RB" || *key == "WR" || *key == "TE" {
@@ -156,6 +259,9 @@ struct DKDataRow {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct FanDuelDataRow {
+    #[serde(rename = "Id")]
+    id: String,
+
     #[serde(rename = "Position")]
     position: String,
     
@@ -176,6 +282,89 @@ pub struct FanDuelDataRow {
 
     #[serde(rename = "Opponent")]
     opponent: String,
+
+    #[serde(rename = "Injury Indicator", default)]
+    injury_indicator: String,
+
+    #[serde(rename = "Injury Details", default)]
+    injury_details: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contest_reader::load_contest;
+
+    #[test]
+    fn dk_showdown_base_salary() {
+        let category_map = category_mapper::map_categories("nfl").unwrap();
+        let mut builder_state = BuilderState::default();
+        load_contest("../resources/game_templates/draft_kings/nfl/showdown.json", &mut builder_state);
+        read_slate("../data/dk_nfl_showdown.csv", "draft_kings", &mut builder_state, &category_map).unwrap();
+        let player_pool = builder_state.player_pool.unwrap();
+        let captain = player_pool.get_player(&13041956).unwrap();
+        let flex = player_pool.get_player(&13041891).unwrap();
+        assert_eq!(captain.price, 12600);
+        assert_eq!(captain.price, flex.price);
+        assert_eq!(captain.projected_points, flex.projected_points);
+        assert_eq!(captain.categories, hashset!{*category_map.get("CPT").unwrap()});
+    }
+
+    #[test]
+    fn fd_provider_id() {
+        let category_map = category_mapper::map_categories("nfl").unwrap();
+        let mut builder_state = BuilderState::default();
+        load_contest("../resources/game_templates/fanduel/nfl/classic.json", &mut builder_state);
+        read_slate("../data/fd_nfl_full.csv", "fanduel", &mut builder_state, &category_map).unwrap();
+        let player_pool = builder_state.player_pool.unwrap();
+        let player = player_pool.get_player(&1).unwrap();
+        assert_eq!(player.provider_id, "37319-104449");
+        assert_eq!(player.name, "Joe Horn Jr.");
+    }
+
+    #[test]
+    fn fd_injury_indicator() {
+        let category_map = category_mapper::map_categories("nfl").unwrap();
+        let mut builder_state = BuilderState::default();
+        load_contest("../resources/game_templates/fanduel/nfl/classic.json", &mut builder_state);
+        read_slate("../data/fd_nfl_full.csv", "fanduel", &mut builder_state, &category_map).unwrap();
+        let player_pool = builder_state.player_pool.unwrap();
+        let player = player_pool.iter().map(|(_, p)| p).find(|p| p.provider_id == "37319-64555").unwrap();
+        assert_eq!(player.injury_indicator, "Q");
+        assert_eq!(player.injury_details, "Hamstring");
+        assert_eq!(player_pool.get_player(&1).unwrap().injury_indicator, "");
+    }
+
+    #[test]
+    fn opponent_from_game_info() {
+        let game_info = parse_game_info("NYG@DAL 09/08/2019 04:25PM ET");
+        assert_eq!(game_info.opponent("NYG"), "DAL");
+        assert_eq!(game_info.opponent("DAL"), "NYG");
+        assert_eq!(game_info.opponent("GB"), "");
+        assert_eq!(parse_game_info("Postponed").opponent("NYG"), "");
+    }
+
+    #[test]
+    fn game_from_game_info() {
+        let game_info = parse_game_info("NYG@DAL 09/08/2019 04:25PM ET");
+        assert_eq!(game_info.game, "NYG@DAL");
+        assert_eq!(game_info.away_team, "NYG");
+        assert_eq!(game_info.home_team, "DAL");
+        assert_eq!(parse_game_info("").game, "");
+        assert_eq!(parse_game_info("JAC@BAL").home_team, "BAL");
+    }
+
+    #[test]
+    fn start_time_from_game_info() {
+        let start_time = parse_game_info("NYG@DAL 09/08/2019 04:25PM ET").start_time.unwrap();
+        assert_eq!(start_time.to_rfc3339(), "2019-09-08T16:25:00-04:00");
+        let winter = parse_game_info("NYG@DAL 12/08/2019 04:25PM ET").start_time.unwrap();
+        assert_eq!(winter.to_rfc3339(), "2019-12-08T16:25:00-05:00");
+        let pacific = parse_game_info("SEA@LAR 12/08/2019 01:05PM PT").start_time.unwrap();
+        assert_eq!(pacific.to_rfc3339(), "2019-12-08T13:05:00-08:00");
+        assert!(parse_game_info("Postponed").start_time.is_none());
+        assert!(parse_game_info("JAC@BAL").start_time.is_none());
+    }
 }
 
 // #[cfg(test)]
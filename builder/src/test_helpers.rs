@@ -0,0 +1,21 @@
+use std::collections::{ HashMap };
+use crate::common::{ RosterSlot };
+
+/// A QB, RB and FLEX roster with the categories 1, 2 and 5
+pub fn get_test_slots() -> (Vec<RosterSlot>, HashMap<String, u32>) {
+    let roster_slots = ["QB", "RB", "FLEX"].iter().map(|key| RosterSlot {
+        name: key.to_string(),
+        key: key.to_string(),
+        count: 1,
+        salary_multiplier: 1.0,
+        point_multiplier: 1.0,
+        any_position: false,
+        flex: *key == "FLEX",
+    }).collect();
+    let category_map = hashmap!{
+        "QB".to_string() => 1,
+        "RB".to_string() => 2,
+        "FLEX".to_string() => 5,
+    };
+    (roster_slots, category_map)
+}
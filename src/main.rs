@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
+extern crate chrono;
 extern crate csv;
 extern crate serde;
 #[macro_use]
@@ -10,23 +11,190 @@ extern crate clap;
 
 use std::error::Error;
 use std::process;
+use std::thread;
+use chrono::{ DateTime, FixedOffset, Local, NaiveDateTime, TimeZone };
 use clap::{ App, Arg, ArgMatches };
-use builder::builder::{ Builder, Lineup };
+use builder::builder::{ Builder, CorrelationModel, InjuryPolicy, Lineup, ObjectiveMode, PlayerExposure, Randomness, simulate_lineups };
 
-fn run(matches: ArgMatches) -> Result<Vec<Lineup>, String> {
+/// Stack size of the thread that builds and solves the lineups
+const OPTIMIZER_STACK_SIZE: usize = 64 * 1024 * 1024;
+
+fn run(matches: &ArgMatches) -> Result<(Vec<Lineup>, Vec<PlayerExposure>), String> {
     let input_file = matches.value_of("INPUT_FILE").unwrap(); // this is a required parameter
     let provider = matches.value_of("provider").unwrap();
     let contest_type = matches.value_of("contest-type").unwrap();
     let sport = matches.value_of("sport").unwrap();
+    let count = value_t!(matches, "count", u32).map_err(|err| err.to_string())?;
+    let min_unique = value_t!(matches, "unique", u32).map_err(|err| err.to_string())?;
+
+    let mut builder = Builder::new("./resources/game_templates/");
+    if let Some(exposures) = matches.values_of("exposure") {
+        for exposure in exposures {
+            let (player, min, max) = parse_exposure(exposure)?;
+            builder = builder.exposure(player, min, max);
+        }
+    }
+    if let Some(projections) = matches.values_of("projections") {
+        for projection in projections {
+            let (path, weight) = parse_projection_source(projection);
+            builder = builder.weighted_projections(path, weight);
+        }
+    }
+    if let Some(aliases) = matches.value_of("aliases") {
+        builder = builder.aliases(aliases);
+    }
+    if matches.is_present("ownership-penalty") {
+        let lambda = value_t!(matches, "ownership-penalty", f64).map_err(|err| err.to_string())?;
+        builder = builder.objective(ObjectiveMode::OwnershipPenalty(lambda));
+    }
+    if matches.is_present("max-ownership") {
+        let max_ownership = value_t!(matches, "max-ownership", f64).map_err(|err| err.to_string())?;
+        builder = builder.objective(ObjectiveMode::OwnershipCap(max_ownership));
+    }
+    if let Some(randomness) = matches.value_of("randomness") {
+        builder = builder.randomness(parse_randomness(randomness)?);
+    }
+    if matches.is_present("seed") {
+        let seed = value_t!(matches, "seed", u64).map_err(|err| err.to_string())?;
+        builder = builder.seed(seed);
+    }
+    if matches.is_present("min-salary") {
+        let min_salary = value_t!(matches, "min-salary", u32).map_err(|err| err.to_string())?;
+        builder = builder.min_salary(min_salary);
+    }
+    if let Some(locks) = matches.values_of("lock") {
+        for lock in locks {
+            builder = match lock.parse::<u64>() {
+                Ok(id) => builder.lock(&[id]),
+                Err(_) => builder.lock_by_name(&[lock]),
+            };
+        }
+    }
+    if let Some(excludes) = matches.values_of("exclude") {
+        for exclude in excludes {
+            builder = match exclude.parse::<u64>() {
+                Ok(id) => builder.exclude(&[id]),
+                Err(_) => builder.exclude_by_name(&[exclude]),
+            };
+        }
+    }
+    let mut injury_policy = if matches.is_present("keep-injured") { InjuryPolicy::keep_all() } else { InjuryPolicy::new() };
+    if let Some(penalties) = matches.values_of("injury-penalty") {
+        for penalty in penalties {
+            let (indicator, percent) = parse_injury_penalty(penalty)?;
+            injury_policy = injury_policy.penalize(indicator, percent);
+        }
+    }
+    builder = builder.injury_policy(injury_policy);
+    if let Some(games) = matches.values_of("games") {
+        let games: Vec<&str> = games.collect();
+        builder = builder.games(&games);
+    }
+    if let Some(starts_after) = matches.value_of("starts-after") {
+        builder = builder.starts_after(parse_local_time(starts_after)?);
+    }
+    if let Some(starts_before) = matches.value_of("starts-before") {
+        builder = builder.starts_before(parse_local_time(starts_before)?);
+    }
+    let builder = builder.provider(provider)
+                         .sport(sport)
+                         .contest(contest_type)
+                         .slate(input_file)
+                         .num_lineups(count)
+                         .min_unique(min_unique)
+                         .build().map_err(|err| format!("optimizer build step failed: {}", err))?;
+    let lineups = match matches.value_of("late-swap") {
+        Some(entries_path) => {
+            let now = match matches.value_of("now") {
+                Some(now) => parse_local_time(now)?,
+                None => Local::now().fixed_offset(),
+            };
+            builder.late_swap(entries_path, &now)?
+        },
+        None => builder.optimize_new()?,
+    };
+    if let Some(output) = matches.value_of("output") {
+        builder.write_lineups(output, &lineups)?;
+    }
+    let exposures = builder.exposures(&lineups)?;
+    Ok((lineups, exposures))
+}
+
+/// Prints the simulated score distribution of each lineup when `--simulations` is set
+fn simulate(matches: &ArgMatches, lineups: &[Lineup]) -> Result<(), String> {
+    if !matches.is_present("simulations") {
+        return Ok(());
+    }
+    let iterations = value_t!(matches, "simulations", u32).map_err(|err| err.to_string())?;
+    let seed = if matches.is_present("seed") {
+        Some(value_t!(matches, "seed", u64).map_err(|err| err.to_string())?)
+    } else {
+        None
+    };
+    let correlations = match matches.value_of("correlations") {
+        Some(path) => CorrelationModel::from_file(path)?,
+        None => CorrelationModel::for_sport(matches.value_of("sport").unwrap()),
+    };
+    let results = simulate_lineups(lineups, iterations, seed, &correlations)?;
+    println!("Simulation ({} iterations):", iterations);
+    for (i, result) in results.iter().enumerate() {
+        println!("{}. mean: {:.2}, p90: {:.2}, p99: {:.2}, win: {:.1}%",
+            i + 1, result.mean, result.p90, result.p99, result.win_probability * 100.0);
+    }
+    Ok(())
+}
+
+/// Parses a local time given as YYYY-MM-DD HH:MM
+fn parse_local_time(value: &str) -> Result<DateTime<FixedOffset>, String> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").ok()
+        .and_then(|time| Local.from_local_datetime(&time).earliest())
+        .map(|time| time.fixed_offset())
+        .ok_or_else(|| format!("invalid time '{}', expected YYYY-MM-DD HH:MM", value))
+}
+
+/// Parses a randomness argument, either a percentage or `stddev`
+fn parse_randomness(value: &str) -> Result<Randomness, String> {
+    if value.eq_ignore_ascii_case("stddev") {
+        return Ok(Randomness::StdDev);
+    }
+    value.trim_end_matches('%').parse::<f64>()
+        .map(Randomness::Percent)
+        .map_err(|_| format!("invalid randomness '{}', expected a percentage or stddev", value))
+}
 
-    let builder = Builder::new("./resources/game_templates/");
-    let result = builder.provider(provider)
-                        .sport(sport)
-                        .contest(contest_type)
-                        .slate(input_file)
-                        .build().expect("optimizer build step failed")
-                        .optimize_new();
-    result
+/// Parses a `player:min:max` exposure argument
+fn parse_exposure(value: &str) -> Result<(&str, f64, f64), String> {
+    let parts: Vec<&str> = value.rsplitn(3, ':').collect();
+    if parts.len() != 3 {
+        return Err(format!("invalid exposure '{}', expected player:min:max", value));
+    }
+    let max = parts[0].parse::<f64>().map_err(|_| format!("invalid max exposure in '{}'", value))?;
+    let min = parts[1].parse::<f64>().map_err(|_| format!("invalid min exposure in '{}'", value))?;
+    Ok((parts[2], min, max))
+}
+
+/// Parses an injury penalty given as indicator:percent, i.e. Q:20
+fn parse_injury_penalty(value: &str) -> Result<(&str, f64), String> {
+    let parts: Vec<&str> = value.splitn(2, ':').collect();
+    if parts.len() != 2 {
+        return Err(format!("invalid injury penalty '{}', expected indicator:percent", value));
+    }
+    let percent = parts[1].parse::<f64>().map_err(|_| format!("invalid injury penalty percent in '{}'", value))?;
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(format!("injury penalty in '{}' must be between 0 and 100", value));
+    }
+    Ok((parts[0], percent))
+}
+
+/// Parses a projections file with an optional weight, i.e. `projections.csv:0.5`. A path that contains ':' (i.e.
+/// `C:\projections.csv`) is only split when the part after the last ':' is a number
+fn parse_projection_source(value: &str) -> (&str, f64) {
+    if let Some((path, weight)) = value.rsplit_once(':') {
+        if let Ok(weight) = weight.parse::<f64>() {
+            return (path, weight);
+        }
+    }
+    (value, 1.0)
 }
 
 fn main() {
@@ -50,19 +218,147 @@ fn main() {
                             .short("s")
                             .default_value("nfl")
                             .help("sets the sport type"))
+                        .arg(Arg::with_name("count")
+                            .short("n")
+                            .long("count")
+                            .default_value("1")
+                            .help("the number of distinct lineups to generate"))
+                        .arg(Arg::with_name("unique")
+                            .short("u")
+                            .long("unique")
+                            .default_value("1")
+                            .help("the minimum number of players each lineup must differ by"))
+                        .arg(Arg::with_name("exposure")
+                            .short("e")
+                            .long("exposure")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("limits a player's exposure across lineups as player:min:max percentages, player is an id or name"))
+                        .arg(Arg::with_name("projections")
+                            .long("projections")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("a csv of projections (ID, Name, Team, Projection, Ownership) that overwrites the slate's points, as path[:weight] to blend several files"))
+                        .arg(Arg::with_name("aliases")
+                            .long("aliases")
+                            .takes_value(true)
+                            .help("a csv of team and player aliases (Type, Alias, Name) used to match projections"))
+                        .arg(Arg::with_name("ownership-penalty")
+                            .long("ownership-penalty")
+                            .takes_value(true)
+                            .conflicts_with("max-ownership")
+                            .help("subtracts this multiple of each player's projected ownership from their points"))
+                        .arg(Arg::with_name("max-ownership")
+                            .long("max-ownership")
+                            .takes_value(true)
+                            .help("caps the total projected ownership (sum of percentages) of each lineup"))
+                        .arg(Arg::with_name("min-salary")
+                            .long("min-salary")
+                            .takes_value(true)
+                            .help("the minimum total salary of each lineup"))
+                        .arg(Arg::with_name("lock")
+                            .short("l")
+                            .long("lock")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("forces a player into every lineup, by id or name"))
+                        .arg(Arg::with_name("exclude")
+                            .short("x")
+                            .long("exclude")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("removes a player from consideration, by id or name"))
+                        .arg(Arg::with_name("keep-injured")
+                            .long("keep-injured")
+                            .help("keeps players that are out or on the injured list, they are excluded by default"))
+                        .arg(Arg::with_name("injury-penalty")
+                            .long("injury-penalty")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("reduces the projections of players with an injury indicator by a percentage, i.e. Q:20"))
+                        .arg(Arg::with_name("games")
+                            .long("games")
+                            .takes_value(true)
+                            .multiple(true)
+                            .use_delimiter(true)
+                            .help("only builds from these games, i.e. NYG@DAL,PIT@NE"))
+                        .arg(Arg::with_name("starts-after")
+                            .long("starts-after")
+                            .takes_value(true)
+                            .help("only builds from games starting at or after this local time, as YYYY-MM-DD HH:MM"))
+                        .arg(Arg::with_name("starts-before")
+                            .long("starts-before")
+                            .takes_value(true)
+                            .help("only builds from games starting at or before this local time, as YYYY-MM-DD HH:MM"))
+                        .arg(Arg::with_name("output")
+                            .short("o")
+                            .long("output")
+                            .takes_value(true)
+                            .help("writes the lineups to a csv in the provider's bulk upload format"))
+                        .arg(Arg::with_name("late-swap")
+                            .long("late-swap")
+                            .takes_value(true)
+                            .help("re-optimizes already entered lineups from a csv in the provider's upload format, keeping players whose games have started"))
+                        .arg(Arg::with_name("now")
+                            .long("now")
+                            .takes_value(true)
+                            .requires("late-swap")
+                            .help("the current local time for late swap as YYYY-MM-DD HH:MM, defaults to now"))
+                        .arg(Arg::with_name("simulations")
+                            .long("simulations")
+                            .takes_value(true)
+                            .help("simulates this many slate outcomes and reports the score distribution of each lineup"))
+                        .arg(Arg::with_name("correlations")
+                            .long("correlations")
+                            .takes_value(true)
+                            .help("a csv of player correlations (Position, OtherPosition, Relation, Correlation) used by the simulation instead of the sport's defaults"))
+                        .arg(Arg::with_name("randomness")
+                            .long("randomness")
+                            .takes_value(true)
+                            .help("perturbs projections before each solve by up to this percentage, or by each player's std dev across several --projections files with 'stddev'"))
+                        .arg(Arg::with_name("seed")
+                            .long("seed")
+                            .takes_value(true)
+                            .help("seeds the random number generator for reproducible runs"))
                         .get_matches();
 
     let _config = matches.value_of("config").unwrap_or("default.conf");
 
-    match run(matches) {
-        Ok(lineups) => {
-            for lineup in lineups {
-                println!("{}", lineup.to_string())
+    // lp_modeler builds and simplifies expressions recursively, large slates need more stack than the main thread has
+    let worker = thread::Builder::new()
+        .stack_size(OPTIMIZER_STACK_SIZE)
+        .spawn(move || {
+            match run(&matches) {
+                Ok((lineups, exposures)) => {
+                    for lineup in &lineups {
+                        println!("{}", lineup.to_string())
+                    }
+                    println!("Exposures:");
+                    for exposure in &exposures {
+                        let target = match exposure.target {
+                            Some((min, max)) => format!(" [{}-{}%]{}", min, max, if exposure.missed_min() { " below min" } else { "" }),
+                            None => String::new(),
+                        };
+                        println!("{} ({}): {} ({:.1}%){}", exposure.name, exposure.team, exposure.count, exposure.percent, target);
+                    }
+                    if let Err(err) = simulate(&matches, &lineups) {
+                        println!("{}", err);
+                        process::exit(1);
+                    }
+                },
+                Err(err) => {
+                    println!("{}", err);
+                    process::exit(1);
+                }
             }
-        },
-        Err(err) => {
-            println!("{}", err);
-            process::exit(1);
-        }
+        })
+        .expect("failed to start the optimizer thread");
+    if worker.join().is_err() {
+        process::exit(1);
     }
 }